@@ -1,32 +1,116 @@
 #[macro_use]
 extern crate rocket;
 
-use nova_luna_solver::nova_luna::Tile;
-use nova_luna_solver::SolverParameters;
+use nova_luna_solver::catalog::CatalogTile;
+use nova_luna_solver::nova_luna::TileBudget;
+use nova_luna_solver::{
+    RecommendOptions, Recommendation, Scoring, SolverError, SolverParameters, Strategy,
+    Transpositions,
+};
+use rocket::http::Status;
+use rocket::response::status;
 use rocket::serde::json::Json;
+use std::time::Duration;
 
-#[post("/solve", data = "<tiles>")]
-fn solve_board(tiles: Json<Vec<Tile>>) -> String {
+type SolverResult<T> = Result<T, status::Custom<String>>;
+
+/// Solves the tiles, evaluating the search tree with the evaluator named in the query, e.g.
+/// `/solve?evaluator=rollout`. With a budget, e.g. `/solve?budget=count:4`, the tiles are a pool
+/// to choose from.
+#[post("/solve?<evaluator>&<budget>", data = "<tiles>")]
+fn solve_board(
+    tiles: String,
+    evaluator: Option<&str>,
+    budget: Option<&str>,
+) -> SolverResult<String> {
+    let evaluator = nova_luna_solver::evaluator::by_name(evaluator.unwrap_or("solved"))
+        .map_err(|e| status::Custom(Status::BadRequest, e))?;
+    let budget = budget
+        .map(|b| b.parse::<TileBudget>())
+        .transpose()
+        .map_err(|e| status::Custom(Status::BadRequest, e))?;
     let param = SolverParameters {
         tiles: vec![],
+        budget,
         output_file: None,
         output_dir: None,
         print_statistics: true,
         print_moves: true,
         num_playouts: 10,
+        time_limit: Some(Duration::from_secs(1)),
         num_threads: 1,
         debug: true,
         exploration_constant: 2.0,
+        evaluator,
+        scoring: Scoring::Tokens,
+        strategy: Strategy::Mcts,
+        transpositions: Transpositions::default(),
+        seed: None,
     };
     let parameters = SolverParameters {
-        tiles: tiles.to_vec(),
+        tiles: nova_luna_solver::parse_string(tiles).map_err(error_response)?,
         ..param
     };
-    let state = nova_luna_solver::solve(parameters);
-    nova_luna_solver::game_state_as_json(&state)
+    let state = nova_luna_solver::solve(parameters).map_err(error_response)?;
+    nova_luna_solver::game_state_as_json(&state).map_err(error_response)
+}
+
+/// Recommends the `top` next moves for the board and hand of a game in progress, e.g.
+/// `/recommend?top=3&evaluator=rollout`. A budget, e.g. `budget=cost:4`, limits the tiles placed
+/// from now on.
+#[post("/recommend?<top>&<evaluator>&<budget>", data = "<position>")]
+fn recommend(
+    position: String,
+    top: Option<usize>,
+    evaluator: Option<&str>,
+    budget: Option<&str>,
+) -> SolverResult<Json<Vec<Recommendation>>> {
+    let evaluator = nova_luna_solver::evaluator::by_name(evaluator.unwrap_or("solved"))
+        .map_err(|e| status::Custom(Status::BadRequest, e))?;
+    let budget = budget
+        .map(|b| b.parse::<TileBudget>())
+        .transpose()
+        .map_err(|e| status::Custom(Status::BadRequest, e))?;
+    let (board, hand) = nova_luna_solver::parse_position(position).map_err(error_response)?;
+    let options = RecommendOptions {
+        top: top.unwrap_or(5),
+        num_playouts: 10,
+        time_limit: Some(Duration::from_secs(1)),
+        num_threads: 1,
+        exploration_constant: 2.0,
+        evaluator,
+        scoring: Scoring::Tokens,
+        budget,
+        seed: None,
+    };
+    nova_luna_solver::recommend(&board, hand, &options)
+        .map(Json)
+        .map_err(error_response)
+}
+
+#[get("/catalog")]
+fn catalog() -> Json<Vec<CatalogTile>> {
+    Json(nova_luna_solver::catalog::catalog().to_vec())
+}
+
+fn error_response(error: SolverError) -> status::Custom<String> {
+    let status = match error {
+        SolverError::Io(_) => Status::InternalServerError,
+        SolverError::Syntax(_) => Status::BadRequest,
+        SolverError::Schema(_)
+        | SolverError::UnknownTileId { .. }
+        | SolverError::TooManyTiles(_)
+        | SolverError::TooManyTasks { .. }
+        | SolverError::EmptyTask { .. }
+        | SolverError::CostOutOfRange { .. }
+        | SolverError::IllegalMove { .. }
+        | SolverError::UnavailableTile { .. }
+        | SolverError::UnsupportedVersion(_) => Status::UnprocessableEntity,
+    };
+    status::Custom(status, error.to_string())
 }
 
 #[launch]
 fn rocket() -> _ {
-    rocket::build().mount("/", routes![solve_board])
+    rocket::build().mount("/", routes![solve_board, recommend, catalog])
 }
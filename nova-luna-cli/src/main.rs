@@ -1,6 +1,11 @@
 use clap::{crate_authors, crate_name, crate_version, App, Arg};
-use nova_luna_solver::SolverParameters;
+use nova_luna_solver::nova_luna::TileBudget;
+use nova_luna_solver::{
+    RecommendOptions, Scoring, SolverError, SolverParameters, Strategy, Transpositions,
+};
 use std::io::Read;
+use std::process;
+use std::time::Duration;
 
 fn main() {
     let matches = App::new(crate_name!())
@@ -14,6 +19,49 @@ fn main() {
                 .about("Read tiles from this file in JSON format")
                 .takes_value(true)
         )
+        .arg(
+            Arg::new("TILES")
+                .long("tiles")
+                .about("Select the tiles by their IDs in the built-in catalog, e.g. \"12,40,57\".")
+                .takes_value(true)
+                .use_delimiter(true)
+                .conflicts_with("INPUT_FILE")
+        )
+        .arg(
+            Arg::new("PRINT_CATALOG")
+                .long("catalog")
+                .about("Prints the tiles of the built-in catalog with their IDs.")
+        )
+        .arg(
+            Arg::new("VALIDATE_ONLY")
+                .long("validate-only")
+                .about("Only checks the tiles against the constraints of the printed tiles and reports all problems.")
+        )
+        .arg(
+            Arg::new("VERIFY")
+                .long("verify")
+                .about("Only checks a board saved with --output or a plain list of tiles with their positions, recomputing the solved tasks, and reports all inconsistencies.")
+                .conflicts_with_all(&["TILES", "VALIDATE_ONLY"])
+        )
+        .arg(
+            Arg::new("RECOMMEND")
+                .long("recommend")
+                .about("Recommends the next moves for a game in progress. The input is a JSON object with the \"board\", a list of tiles with their \"position\", and the \"hand\" of remaining tiles.")
+                .conflicts_with("TILES")
+        )
+        .arg(
+            Arg::new("RESUME")
+                .long("resume")
+                .about("Continues a game saved with --output instead of starting with an empty board.")
+                .conflicts_with_all(&["TILES", "RECOMMEND"])
+        )
+        .arg(
+            Arg::new("TOP")
+                .long("top")
+                .default_value("5")
+                .about("Number of moves to recommend.")
+                .takes_value(true)
+        )
         .arg(
             Arg::new("PRINT_STATISTICS")
                 .long("statistics")
@@ -46,6 +94,13 @@ fn main() {
                 .about("Number of playouts to determine the best game board.")
                 .takes_value(true)
         )
+        .arg(
+            Arg::new("TIME")
+                .long("time")
+                .about("Searches until the time limit, e.g. \"2s\" or \"500ms\", instead of doing a fixed number of playouts.")
+                .takes_value(true)
+                .validator(parse_duration)
+        )
         .arg(
             Arg::new("THREADS")
                 .long("threads")
@@ -54,6 +109,52 @@ fn main() {
                 .about("Number of threads used for MCTS.")
                 .takes_value(true)
         )
+        .arg(
+            Arg::new("EVALUATOR")
+                .long("evaluator")
+                .default_value("solved")
+                .about("Evaluation of the leaves of the search tree: the \"solved\" tasks, the \"partial-progress\" of the unsolved tasks, or the average of random \"rollout:<n>\" or \"guided-rollout:<n>\" placements of the remaining tiles.")
+                .takes_value(true)
+                .validator(|s| nova_luna_solver::evaluator::by_name(s).map(|_| ()))
+        )
+        .arg(
+            Arg::new("BUDGET")
+                .long("budget")
+                .about("Treats the tiles as a pool and takes tiles until no further tile fits into the budget: \"count:<tiles>\" or \"cost:<moon track steps>\". The chosen tiles are printed with the result. With --recommend, the budget only limits the tiles placed from now on.")
+                .takes_value(true)
+                .validator(|s| s.parse::<TileBudget>())
+                .conflicts_with("RESUME")
+        )
+        .arg(
+            Arg::new("STRATEGY")
+                .long("strategy")
+                .default_value("mcts")
+                .about("Search placing the tiles: \"mcts\" or \"exact\", which finds the best board but only finishes for up to about eight tiles.")
+                .takes_value(true)
+                .validator(|s| s.parse::<Strategy>())
+        )
+        .arg(
+            Arg::new("TRANSPOSITIONS")
+                .long("transpositions")
+                .default_value("approx")
+                .about("Transposition table of MCTS: \"approx\" sized for --playouts, also with --time, \"approx:<entries>\", \"exact\", which grows with the search tree, or \"off\". Its hit rate and collisions are part of the statistics.")
+                .takes_value(true)
+                .validator(|s| s.parse::<Transpositions>())
+        )
+        .arg(
+            Arg::new("SCORING")
+                .long("scoring")
+                .default_value("tokens")
+                .about("Scoring of the boards: \"tokens\" placed, \"tokens-per-cost\" of the moon track or \"weighted:<tokens>:<tokens-per-cost>\".")
+                .takes_value(true)
+                .validator(|s| s.parse::<Scoring>())
+        )
+        .arg(
+            Arg::new("SEED")
+                .long("seed")
+                .about("Makes the search reproducible. Every thread searches its own tree seeded with this value plus its index.")
+                .takes_value(true)
+        )
         .arg(
             Arg::new("DEBUG")
                 .long("debug")
@@ -76,37 +177,202 @@ fn main() {
     let num_playouts = matches
         .value_of_t("PLAYOUTS")
         .expect("cannot read playouts");
+    let time_limit = matches
+        .value_of("TIME")
+        .map(|time| parse_duration(time).expect("validated by clap"));
     let exploration_constant = matches
         .value_of_t("EXPLORATION_CONSTANT")
         .expect("cannot read UTC policy constant");
+    let evaluator = nova_luna_solver::evaluator::by_name(
+        matches
+            .value_of("EVALUATOR")
+            .expect("evaluator has a default value"),
+    )
+    .expect("validated by clap");
+    let scoring = matches.value_of_t("SCORING").expect("cannot read scoring");
+    let budget = matches
+        .is_present("BUDGET")
+        .then(|| matches.value_of_t("BUDGET").unwrap_or_else(|e| e.exit()));
+    let strategy = matches
+        .value_of_t("STRATEGY")
+        .expect("cannot read strategy");
+    let transpositions = matches
+        .value_of_t("TRANSPOSITIONS")
+        .expect("cannot read transposition table");
     let debug = matches.is_present("DEBUG");
+    let seed = matches
+        .is_present("SEED")
+        .then(|| matches.value_of_t("SEED").unwrap_or_else(|e| e.exit()));
 
     let param = SolverParameters {
         tiles: vec![],
+        budget,
         output_file,
         output_dir,
         print_statistics,
         print_moves,
         num_playouts,
+        time_limit,
         num_threads,
         debug,
         exploration_constant,
+        evaluator,
+        scoring,
+        strategy,
+        transpositions,
+        seed,
     };
 
-    let tiles = match matches.value_of("INPUT_FILE") {
-        Some(path) => nova_luna_solver::parse_file(path),
-        None => nova_luna_solver::parse_string(read_from_stdin()),
+    if matches.is_present("PRINT_CATALOG") {
+        for tile in nova_luna_solver::catalog::catalog() {
+            match serde_json::to_string(&tile) {
+                Ok(json) => println!("{}", json),
+                Err(e) => exit_with_error(e.into()),
+            }
+        }
+        return;
+    }
+
+    let input = if matches.is_present("TILES") {
+        let ids: Vec<usize> = matches.values_of_t("TILES").unwrap_or_else(|e| e.exit());
+        serde_json::to_string(&ids).map_err(SolverError::from)
+    } else {
+        match matches.value_of("INPUT_FILE") {
+            Some(path) => std::fs::read_to_string(path).map_err(SolverError::from),
+            None => read_from_stdin(),
+        }
     };
 
-    nova_luna_solver::solve(SolverParameters { tiles, ..param });
+    if matches.is_present("RECOMMEND") {
+        let top = matches.value_of_t("TOP").unwrap_or_else(|e| e.exit());
+        let options = RecommendOptions {
+            top,
+            num_playouts: param.num_playouts,
+            time_limit: param.time_limit,
+            num_threads: param.num_threads,
+            exploration_constant: param.exploration_constant,
+            evaluator: param.evaluator,
+            scoring: param.scoring,
+            budget: param.budget,
+            seed: param.seed,
+        };
+        let recommendations = input
+            .and_then(nova_luna_solver::parse_position)
+            .and_then(|(board, hand)| nova_luna_solver::recommend(&board, hand, &options));
+        match recommendations {
+            Ok(recommendations) => {
+                println!("# Recommendations:");
+                for recommendation in recommendations {
+                    match serde_json::to_string(&recommendation) {
+                        Ok(json) => println!("{}", json),
+                        Err(e) => exit_with_error(e.into()),
+                    }
+                }
+            }
+            Err(e) => exit_with_error(e),
+        }
+        return;
+    }
+
+    if matches.is_present("VERIFY") {
+        match input.and_then(nova_luna_solver::verify_string) {
+            Ok(discrepancies) if discrepancies.is_empty() => {}
+            Ok(discrepancies) => {
+                for discrepancy in discrepancies {
+                    println!("{}", discrepancy);
+                }
+                process::exit(65);
+            }
+            Err(e) => exit_with_error(e),
+        }
+        return;
+    }
+
+    if matches.is_present("VALIDATE_ONLY") {
+        match input.and_then(nova_luna_solver::validate_string) {
+            Ok(diagnostics) if diagnostics.is_empty() => {}
+            Ok(diagnostics) => {
+                for diagnostic in diagnostics {
+                    println!("{}", diagnostic);
+                }
+                process::exit(65);
+            }
+            Err(e) => exit_with_error(e),
+        }
+        return;
+    }
+
+    let result = if matches.is_present("RESUME") {
+        input
+            .and_then(nova_luna_solver::load_state)
+            .and_then(|state| nova_luna_solver::resume(state, param.clone()))
+    } else {
+        input
+            .and_then(nova_luna_solver::parse_string)
+            .and_then(|tiles| {
+                nova_luna_solver::solve(SolverParameters {
+                    tiles,
+                    ..param.clone()
+                })
+            })
+    };
+
+    let output = result.and_then(|game| {
+        if game.budget().is_some() {
+            let tiles = nova_luna_solver::chosen_tiles(&game);
+            println!("# Chosen tiles:\n{}", serde_json::to_string(&tiles)?);
+        }
+        match param.output_file {
+            Some(_) => nova_luna_solver::output_game_state(&param, &game),
+            None => Ok(()),
+        }
+    });
+    if let Err(e) = output {
+        exit_with_error(e);
+    }
+}
+
+fn exit_with_error(error: SolverError) -> ! {
+    eprintln!("error: {}", error);
+    process::exit(exit_code(&error));
+}
+
+/// Maps errors to the exit codes defined in `sysexits.h`.
+fn exit_code(error: &SolverError) -> i32 {
+    match error {
+        SolverError::Io(_) => 74,
+        SolverError::Syntax(_)
+        | SolverError::Schema(_)
+        | SolverError::UnknownTileId { .. }
+        | SolverError::TooManyTiles(_)
+        | SolverError::TooManyTasks { .. }
+        | SolverError::EmptyTask { .. }
+        | SolverError::CostOutOfRange { .. }
+        | SolverError::UnavailableTile { .. }
+        | SolverError::UnsupportedVersion(_) => 65,
+        SolverError::IllegalMove { .. } => 70,
+    }
+}
+
+/// Parses durations like "2s", "1.5s", "500ms" or "1m". Numbers without unit are seconds.
+fn parse_duration(input: &str) -> Result<Duration, String> {
+    let (number, factor) = if let Some(ms) = input.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(s) = input.strip_suffix('s') {
+        (s, 1.0)
+    } else if let Some(m) = input.strip_suffix('m') {
+        (m, 60.0)
+    } else {
+        (input, 1.0)
+    };
+    match number.trim().parse::<f64>() {
+        Ok(n) if n.is_finite() && n >= 0.0 => Ok(Duration::from_secs_f64(n * factor)),
+        _ => Err(format!("\"{}\" is not a valid duration", input)),
+    }
 }
 
-fn read_from_stdin() -> String {
-    let stdin = std::io::stdin();
-    let mut input = vec![];
-    stdin
-        .lock()
-        .read_to_end(&mut input)
-        .expect("cannot read from stdin");
-    String::from_utf8(input).expect("cannot read from stdin")
+fn read_from_stdin() -> Result<String, SolverError> {
+    let mut input = String::new();
+    std::io::stdin().lock().read_to_string(&mut input)?;
+    Ok(input)
 }
@@ -0,0 +1,41 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use mcts::GameState;
+use nova_luna_solver::nova_luna::State;
+
+/// Plays the shipped tiles until the pool is empty, cloning the state before every move like
+/// the search does when descending the tree.
+fn playout(state: &State, seed: usize) -> State {
+    let mut state = state.clone();
+    let mut n = seed;
+    loop {
+        let moves = state.available_moves();
+        if moves.is_empty() {
+            return state;
+        }
+        n = n
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let mut next = state.clone();
+        next.make_move(&moves[(n >> 33) % moves.len()]);
+        state = next;
+    }
+}
+
+fn playouts(c: &mut Criterion) {
+    let tiles = nova_luna_solver::parse_file("../resources/tiles.json").unwrap();
+    let state = State::with_tiles(tiles);
+
+    let mut group = c.benchmark_group("playouts");
+    group.throughput(Throughput::Elements(1));
+    let mut seed = 0;
+    group.bench_function("random playout", |b| {
+        b.iter(|| {
+            seed += 1;
+            playout(&state, seed)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, playouts);
+criterion_main!(benches);
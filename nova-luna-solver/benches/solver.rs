@@ -0,0 +1,166 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use mcts::transposition_table::TranspositionHash;
+use mcts::GameState;
+use nova_luna_solver::evaluator::SolvedTasks;
+use nova_luna_solver::nova_luna::{State, Tile};
+use nova_luna_solver::{Scoring, SolverParameters, Strategy, Transpositions};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::sync::Arc;
+
+const SEED: u64 = 42;
+
+fn tiles() -> Vec<Tile> {
+    nova_luna_solver::parse_file("../resources/tiles.json").expect("cannot read shipped tiles")
+}
+
+/// Plays random moves until only `remaining` tiles are left to place.
+fn random_state(rng: &mut StdRng, remaining: usize) -> State {
+    let mut state = State::with_tiles(tiles());
+    while state.remaining_tiles().count() > remaining {
+        let moves = state.available_moves();
+        state.make_move(moves.choose(rng).unwrap());
+    }
+    state
+}
+
+/// States after 0, 3, 6 and 9 of the 11 shipped tiles have been placed.
+fn states() -> Vec<(usize, State)> {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    [11, 8, 5, 2]
+        .iter()
+        .map(|remaining| (11 - remaining, random_state(&mut rng, *remaining)))
+        .collect()
+}
+
+fn available_moves(c: &mut Criterion) {
+    let mut group = c.benchmark_group("available_moves");
+    for (placed, state) in states() {
+        group.bench_function(format!("{} placed", placed), |b| {
+            b.iter(|| state.available_moves())
+        });
+    }
+    group.finish();
+}
+
+fn make_move(c: &mut Criterion) {
+    let mut group = c.benchmark_group("make_move");
+    for (placed, state) in states() {
+        let moves = state.available_moves();
+        group.throughput(Throughput::Elements(1));
+        group.bench_function(format!("{} placed", placed), |b| {
+            // Each run places one of the moves on a fresh copy of the state.
+            let mut next = moves.iter().cycle();
+            b.iter_batched_ref(
+                || (state.clone(), *next.next().unwrap()),
+                |(state, m)| state.make_move(m),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn unmake_move(c: &mut Criterion) {
+    let mut group = c.benchmark_group("make_move and unmake_move");
+    for (placed, state) in states() {
+        let moves = state.available_moves();
+        group.throughput(Throughput::Elements(moves.len() as u64));
+        group.bench_function(format!("{} placed", placed), |b| {
+            b.iter_batched_ref(
+                || state.clone(),
+                |state| {
+                    for m in &moves {
+                        state.make_move(m);
+                        state.unmake_move();
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// Places every available move and counts the solved tasks, which `make_move` checks
+/// incrementally.
+fn solved_tasks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("solved tasks");
+    for (placed, state) in states() {
+        let moves = state.available_moves();
+        group.throughput(Throughput::Elements(moves.len() as u64));
+        group.bench_function(format!("{} placed", placed), |b| {
+            b.iter(|| {
+                moves
+                    .iter()
+                    .map(|m| {
+                        let mut next = state.clone();
+                        next.make_move(m);
+                        next.count_solved_tasks()
+                    })
+                    .sum::<usize>()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hash");
+    for (placed, state) in states() {
+        group.bench_function(format!("{} placed", placed), |b| {
+            b.iter(|| TranspositionHash::hash(&state))
+        });
+    }
+    group.finish();
+}
+
+fn solve(c: &mut Criterion) {
+    let mut group = c.benchmark_group("solve");
+    group.sample_size(10);
+    for num_playouts in [1000, 10000] {
+        group.throughput(Throughput::Elements(num_playouts as u64));
+        group.bench_function(format!("{} playouts", num_playouts), |b| {
+            b.iter(|| {
+                nova_luna_solver::solve(SolverParameters {
+                    tiles: tiles(),
+                    budget: None,
+                    output_file: None,
+                    output_dir: None,
+                    print_statistics: false,
+                    print_moves: false,
+                    num_playouts,
+                    time_limit: None,
+                    num_threads: 1,
+                    debug: false,
+                    exploration_constant: 2.0,
+                    evaluator: Arc::new(SolvedTasks),
+                    scoring: Scoring::Tokens,
+                    strategy: Strategy::Mcts,
+                    transpositions: Transpositions::default(),
+                    seed: Some(SEED),
+                })
+                .unwrap()
+            })
+        });
+    }
+    for num_tiles in [5, 6] {
+        let state = State::with_tiles(tiles()[..num_tiles].to_vec());
+        group.bench_function(format!("exact {} tiles", num_tiles), |b| {
+            b.iter(|| nova_luna_solver::solve_exact(&state, &Scoring::Tokens))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    available_moves,
+    make_move,
+    unmake_move,
+    solved_tasks,
+    hash,
+    solve
+);
+criterion_main!(benches);
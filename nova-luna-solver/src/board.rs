@@ -0,0 +1,122 @@
+use crate::nova_luna::{Position, MAX_TILES};
+use std::fmt;
+
+/// Width and height of the grid of a game with [`MAX_TILES`] tiles, the largest grid of a board.
+pub(crate) const MAX_SIZE: usize = 2 * MAX_TILES + 1;
+const EMPTY: u8 = u8::MAX;
+
+/// Grid of the board storing the index of the placement on every occupied position.
+///
+/// The grid is centred on the first placed tile and is `2 * n + 1` positions wide and high for a
+/// game of `n` tiles, so every layout of the tiles and the free positions around it stay on the
+/// grid.
+#[derive(Clone)]
+pub(crate) struct Board {
+    size: usize,
+    /// Position of the cell in the centre of the grid.
+    center: Position,
+    cells: Box<[u8]>,
+}
+
+impl Board {
+    /// Creates an empty grid for a game of `num_tiles` tiles.
+    pub fn new(num_tiles: usize) -> Board {
+        let size = 2 * num_tiles + 1;
+        Board {
+            size,
+            center: Position(0, 0),
+            cells: vec![EMPTY; size * size].into(),
+        }
+    }
+
+    /// Moves the centre of the empty grid to the position of the first tile.
+    pub fn center_on(&mut self, pos: Position) {
+        debug_assert!(self.cells.iter().all(|c| *c == EMPTY));
+        self.center = pos;
+    }
+
+    /// Returns the index of the position in the grid, if the position is on the grid.
+    pub fn cell(&self, pos: Position) -> Option<usize> {
+        let half = (self.size / 2) as isize;
+        let x = pos.0 - self.center.0 + half;
+        let y = pos.1 - self.center.1 + half;
+        if (0..self.size as isize).contains(&x) && (0..self.size as isize).contains(&y) {
+            Some(y as usize * self.size + x as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn position(&self, cell: usize) -> Position {
+        let (x, y) = self.coordinates(cell);
+        let half = (self.size / 2) as isize;
+        Position(
+            x as isize - half + self.center.0,
+            y as isize - half + self.center.1,
+        )
+    }
+
+    /// Returns the cell on the column and the row.
+    pub fn cell_at(&self, (x, y): (usize, usize)) -> usize {
+        y * self.size + x
+    }
+
+    /// Returns the column and the row of the cell.
+    pub fn coordinates(&self, cell: usize) -> (usize, usize) {
+        (cell % self.size, cell / self.size)
+    }
+
+    /// Returns the index of the placement on the position.
+    pub fn get(&self, pos: Position) -> Option<usize> {
+        self.cell(pos).and_then(|cell| self.get_cell(cell))
+    }
+
+    pub fn get_cell(&self, cell: usize) -> Option<usize> {
+        match self.cells[cell] {
+            EMPTY => None,
+            placement => Some(placement as usize),
+        }
+    }
+
+    pub fn set_cell(&mut self, cell: usize, placement: usize) {
+        debug_assert!(placement < EMPTY as usize);
+        self.cells[cell] = placement as u8;
+    }
+
+    pub fn clear_cell(&mut self, cell: usize) {
+        self.cells[cell] = EMPTY;
+    }
+}
+
+impl fmt::Debug for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                (0..self.cells.len())
+                    .filter_map(|cell| self.get_cell(cell).map(|p| (self.position(cell), p))),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cells() {
+        let mut board = Board::new(3);
+        for pos in [Position(0, 0), Position(-3, 3), Position(3, -3)] {
+            assert_eq!(pos, board.position(board.cell(pos).unwrap()));
+        }
+        assert_eq!(None, board.cell(Position(4, 0)));
+        assert_eq!(None, board.cell(Position(0, -4)));
+
+        board.center_on(Position(100, -50));
+        assert_eq!(None, board.cell(Position(0, 0)));
+        board.set_cell(board.cell(Position(101, -48)).unwrap(), 7);
+        assert_eq!(Some(7), board.get(Position(101, -48)));
+        assert_eq!(None, board.get(Position(101, -47)));
+        assert_eq!(None, board.get(Position(0, 0)));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::error::SolverError;
+use crate::nova_luna::Tile;
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::sync::OnceLock;
+
+const CATALOG_JSON: &str = include_str!("../resources/catalog.json");
+
+static CATALOG: OnceLock<Vec<CatalogTile>> = OnceLock::new();
+
+/// A printed Nova Luna tile with a stable ID.
+///
+/// IDs are never reused or renumbered, so inputs referring to them stay valid when further
+/// tiles are added to the catalog.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CatalogTile {
+    pub id: usize,
+    #[serde(flatten)]
+    pub tile: Tile,
+}
+
+/// Returns all tiles of the built-in catalog ordered by their ID.
+///
+/// The catalog is parsed on the first call only.
+pub fn catalog() -> &'static [CatalogTile] {
+    CATALOG.get_or_init(|| serde_json::from_str(CATALOG_JSON).expect("built-in catalog is valid"))
+}
+
+/// Returns the tile of the built-in catalog with the given ID.
+pub fn catalog_tile(id: usize) -> Option<Tile> {
+    catalog()
+        .iter()
+        .find(|t| t.id == id)
+        .map(|t| t.tile.clone())
+}
+
+/// A tile in the input, either given by its ID in the catalog or as a full JSON object.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum TileRef {
+    Id(usize),
+    Tile(Tile),
+}
+
+impl<'de> Deserialize<'de> for TileRef {
+    fn deserialize<D>(deserializer: D) -> Result<TileRef, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TileRefVisitor;
+
+        impl<'de> Visitor<'de> for TileRefVisitor {
+            type Value = TileRef;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a catalog ID or a tile")
+            }
+
+            fn visit_u64<E: de::Error>(self, id: u64) -> Result<TileRef, E> {
+                Ok(TileRef::Id(id as usize))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<TileRef, A::Error> {
+                Tile::deserialize(de::value::MapAccessDeserializer::new(map)).map(TileRef::Tile)
+            }
+        }
+
+        deserializer.deserialize_any(TileRefVisitor)
+    }
+}
+
+/// Replaces the catalog IDs in the input with the tiles of the catalog. The solved flags of
+/// tiles given as objects are cleared, so tiles only differing in them are identical.
+pub(crate) fn resolve(tiles: Vec<TileRef>) -> Result<Vec<Tile>, SolverError> {
+    let catalog = catalog();
+    tiles
+        .into_iter()
+        .enumerate()
+        .map(|(idx, tile)| match tile {
+            TileRef::Tile(tile) => Ok(tile.with_solved_flags(0)),
+            TileRef::Id(id) => catalog
+                .iter()
+                .find(|t| t.id == id)
+                .map(|t| t.tile.clone())
+                .ok_or(SolverError::UnknownTileId { tile: idx, id }),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::validation::validate_tiles;
+    use std::collections::HashSet;
+
+    #[test]
+    fn catalog_is_valid() {
+        let catalog = catalog();
+        let ids: HashSet<usize> = catalog.iter().map(|t| t.id).collect();
+        assert_eq!(catalog.len(), ids.len());
+        assert!(catalog.windows(2).all(|w| w[0].id < w[1].id));
+        assert_eq!(68, catalog.len());
+        assert_eq!((1..=68).collect::<HashSet<_>>(), ids);
+
+        let tiles: Vec<Tile> = catalog.iter().map(|t| t.tile.clone()).collect();
+        assert!(validate_tiles(&tiles).is_empty());
+    }
+
+    #[test]
+    fn catalog_ids_are_stable() {
+        let tiles = crate::parse_file("../resources/tiles.json").unwrap();
+        for (idx, tile) in tiles.into_iter().enumerate() {
+            assert_eq!(Some(tile), catalog_tile(idx + 1));
+        }
+        assert_eq!(None, catalog_tile(0));
+    }
+
+    #[test]
+    fn parse_catalog_ids() {
+        let tiles = crate::parse_string(
+            r#"[3, {"color": "Red", "cost": 1, "tasks": [["Blue"]]}, 5]"#.to_string(),
+        )
+        .unwrap();
+        assert_eq!(3, tiles.len());
+        assert_eq!(catalog_tile(3).as_ref(), tiles.first());
+        assert_eq!(catalog_tile(5).as_ref(), tiles.last());
+
+        let tiles = crate::parse_string(
+            r#"[{"color": "Red", "cost": 1, "tasks": [{"colors": ["Blue"], "solved": true}]},
+                {"color": "Red", "cost": 1, "tasks": [["Blue"]]}]"#
+                .to_string(),
+        )
+        .unwrap();
+        assert_eq!(tiles[0], tiles[1]);
+
+        assert!(matches!(
+            crate::parse_string("[1, 1000]".to_string()),
+            Err(SolverError::UnknownTileId { tile: 1, id: 1000 })
+        ));
+        assert!(matches!(
+            crate::parse_string("[-1]".to_string()),
+            Err(SolverError::Schema(_))
+        ));
+    }
+}
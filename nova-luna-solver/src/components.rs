@@ -0,0 +1,98 @@
+use crate::nova_luna::MAX_TILES;
+
+/// Union-find over the placements of the board, joining adjacent tiles of the same color.
+///
+/// Placing a tile only merges components. Taking back the last tile splits them again by
+/// undoing its merges in reverse order.
+#[derive(Clone, Debug)]
+pub(crate) struct Components {
+    parent: [u8; MAX_TILES],
+    size: [u8; MAX_TILES],
+}
+
+impl Components {
+    pub fn insert(&mut self, placement: usize) {
+        self.parent[placement] = placement as u8;
+        self.size[placement] = 1;
+    }
+
+    /// Returns the representative of the component containing the placement.
+    pub fn find(&self, placement: usize) -> usize {
+        let mut placement = placement;
+        while self.parent[placement] as usize != placement {
+            placement = self.parent[placement] as usize;
+        }
+        placement
+    }
+
+    /// Joins the components of both placements and returns the representative which was
+    /// attached to the other one, or `None` if they already were joined.
+    ///
+    /// Joining by size keeps the trees shallow without path compression, so `find` does not
+    /// need to mutate the structure and a join can be undone by [`Components::split`].
+    pub fn union(&mut self, a: usize, b: usize) -> Option<usize> {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return None;
+        }
+        let (root, child) = if self.size[a] >= self.size[b] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        self.parent[child] = root as u8;
+        self.size[root] += self.size[child];
+        Some(child)
+    }
+
+    /// Undoes the join which attached the representative `child`. Joins have to be undone in
+    /// the reverse order they were made.
+    pub fn split(&mut self, child: usize) {
+        let root = self.parent[child] as usize;
+        self.size[root] -= self.size[child];
+        self.parent[child] = child as u8;
+    }
+
+    /// Returns the number of tiles in the component containing the placement.
+    pub fn size(&self, placement: usize) -> usize {
+        self.size[self.find(placement)] as usize
+    }
+}
+
+impl Default for Components {
+    fn default() -> Components {
+        Components {
+            parent: [0; MAX_TILES],
+            size: [0; MAX_TILES],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn union_find() {
+        let mut components = Components::default();
+        for placement in 0..4 {
+            components.insert(placement);
+        }
+        assert_eq!(1, components.size(0));
+
+        components.union(0, 1);
+        components.union(2, 3);
+        assert_eq!(2, components.size(1));
+        assert_ne!(components.find(1), components.find(2));
+
+        let child = components.union(1, 3).unwrap();
+        assert_eq!(4, components.size(0));
+        assert_eq!(components.find(0), components.find(3));
+        assert_eq!(None, components.union(0, 3));
+
+        components.split(child);
+        assert_eq!(2, components.size(1));
+        assert_eq!(2, components.size(3));
+        assert_ne!(components.find(1), components.find(2));
+    }
+}
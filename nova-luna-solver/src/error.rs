@@ -0,0 +1,110 @@
+use crate::catalog;
+use crate::nova_luna::{Position, Tile};
+use std::fmt;
+
+#[derive(Debug)]
+pub enum SolverError {
+    /// Reading the input or writing the output failed.
+    Io(std::io::Error),
+    /// The input is not valid JSON.
+    Syntax(serde_json::Error),
+    /// The input is valid JSON but does not describe tiles, e.g. it contains an unknown color.
+    Schema(serde_json::Error),
+    /// A tile of the input refers to an ID which is not part of the catalog.
+    UnknownTileId { tile: usize, id: usize },
+    /// There are more tiles than the solver can handle.
+    TooManyTiles(usize),
+    /// A tile has more tasks than the solver can handle.
+    TooManyTasks { tile: usize, tasks: usize },
+    /// A task of a tile does not require any color.
+    EmptyTask { tile: usize, task: usize },
+    /// The cost of a tile is outside of the range printed on the tiles.
+    CostOutOfRange { tile: usize, cost: usize },
+    /// A move places a tile which is not available or on a position which is not free. The tile
+    /// is `None` if it is not part of the game.
+    IllegalMove {
+        tile: Option<Tile>,
+        position: Position,
+    },
+    /// A tile of a draw pile is not part of the game or all tiles like it are used up.
+    UnavailableTile { tile: usize },
+    /// A saved state has a schema version this version of the solver cannot read.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SolverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolverError::Io(e) => write!(f, "i/o error: {}", e),
+            SolverError::Syntax(e) => write!(f, "invalid json: {}", e),
+            SolverError::Schema(e) => write!(f, "invalid tiles: {}", e),
+            SolverError::UnknownTileId { tile, id } => {
+                write!(
+                    f,
+                    "invalid tiles: tile {} has unknown catalog id {}",
+                    tile, id
+                )
+            }
+            SolverError::TooManyTiles(n) => write!(f, "invalid tiles: {} tiles are too many", n),
+            SolverError::TooManyTasks { tile, tasks } => write!(
+                f,
+                "invalid tiles: {} tasks of tile {} are too many",
+                tasks, tile
+            ),
+            SolverError::EmptyTask { tile, task } => {
+                write!(f, "invalid tiles: task {} of tile {} is empty", task, tile)
+            }
+            SolverError::CostOutOfRange { tile, cost } => write!(
+                f,
+                "invalid tiles: cost {} of tile {} is out of range",
+                cost, tile
+            ),
+            SolverError::IllegalMove { tile, position } => {
+                let id = tile
+                    .as_ref()
+                    .and_then(|tile| catalog::catalog().iter().find(|t| t.tile == *tile));
+                match (id, tile) {
+                    (Some(t), _) => write!(f, "illegal move: catalog tile {}", t.id)?,
+                    (None, Some(tile)) => {
+                        let json = serde_json::to_string(tile).map_err(|_| fmt::Error)?;
+                        write!(f, "illegal move: tile {}", json)?
+                    }
+                    (None, None) => write!(f, "illegal move: unknown tile")?,
+                }
+                write!(f, " at ({}, {})", position.0, position.1)
+            }
+            SolverError::UnavailableTile { tile } => {
+                write!(f, "invalid tiles: tile {} is not left in the game", tile)
+            }
+            SolverError::UnsupportedVersion(v) => {
+                write!(f, "invalid state: unsupported schema version {}", v)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SolverError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SolverError::Io(e) => Some(e),
+            SolverError::Syntax(e) | SolverError::Schema(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SolverError {
+    fn from(e: std::io::Error) -> SolverError {
+        SolverError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SolverError {
+    fn from(e: serde_json::Error) -> SolverError {
+        match e.classify() {
+            serde_json::error::Category::Io => SolverError::Io(e.into()),
+            serde_json::error::Category::Data => SolverError::Schema(e),
+            _ => SolverError::Syntax(e),
+        }
+    }
+}
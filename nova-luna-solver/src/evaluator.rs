@@ -0,0 +1,205 @@
+//! Strategies to estimate the value of the boards at the leaves of the search tree.
+//!
+//! The search calls the [`LeafEvaluator`] of the [`SolverParameters`](crate::SolverParameters)
+//! once for every new node. Besides the built-in evaluators, which can be selected with
+//! [`by_name`], any type implementing the trait can be used.
+
+use crate::nova_luna::{Move, State};
+use crate::scoring::Scoring;
+use mcts::GameState;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use std::sync::Arc;
+
+/// Names of the built-in evaluators. The number of rollouts per leaf can be appended to the
+/// rollout evaluators, e.g. `rollout:8`.
+pub const NAMES: [&str; 4] = ["solved", "partial-progress", "rollout", "guided-rollout"];
+
+/// Estimates the score a board will reach once all tiles are placed.
+pub trait LeafEvaluator: Send + Sync {
+    /// Returns the estimated score of the state, measured by the scoring. Randomized
+    /// evaluators draw from the random number generator of the search thread, so seeded
+    /// searches stay reproducible.
+    fn evaluate(&self, state: &State, scoring: &Scoring, rng: &mut StdRng) -> f64;
+}
+
+/// Returns the built-in evaluator with the name.
+pub fn by_name(input: &str) -> Result<Arc<dyn LeafEvaluator>, String> {
+    let (name, rollouts) = match input.split_once(':') {
+        Some((name, rollouts)) => match rollouts.parse() {
+            Ok(rollouts) if rollouts > 0 => (name, Some(rollouts)),
+            _ => return Err(format!("invalid number of rollouts in \"{}\"", input)),
+        },
+        None => (input, None),
+    };
+    let rollout = |guided| RandomRollout {
+        rollouts: rollouts.unwrap_or(1),
+        guided,
+    };
+    match (name, rollouts) {
+        ("solved", None) => Ok(Arc::new(SolvedTasks)),
+        ("partial-progress", None) => Ok(Arc::new(PartialProgress::default())),
+        ("rollout", _) => Ok(Arc::new(rollout(false))),
+        ("guided-rollout", _) => Ok(Arc::new(rollout(true))),
+        _ => Err(format!(
+            "unknown evaluator \"{}\" (expected one of {})",
+            input,
+            NAMES.join(", ")
+        )),
+    }
+}
+
+/// Scores the board as it is, ignoring the tasks the remaining tiles could still solve.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SolvedTasks;
+
+impl LeafEvaluator for SolvedTasks {
+    fn evaluate(&self, state: &State, scoring: &Scoring, _: &mut StdRng) -> f64 {
+        scoring.score(state)
+    }
+}
+
+/// Adds the progress of the unsolved tasks to the score, so boards with tasks close to being
+/// solved are preferred over boards without them.
+#[derive(Clone, Copy, Debug)]
+pub struct PartialProgress {
+    /// Value of a task missing no color, relative to a solved task.
+    pub weight: f64,
+}
+
+impl Default for PartialProgress {
+    fn default() -> PartialProgress {
+        PartialProgress { weight: 0.5 }
+    }
+}
+
+impl LeafEvaluator for PartialProgress {
+    fn evaluate(&self, state: &State, scoring: &Scoring, _: &mut StdRng) -> f64 {
+        scoring.score(state) + self.weight * state.task_progress()
+    }
+}
+
+/// Places the remaining tiles at random until the pool is empty and averages the scores of the
+/// final boards.
+#[derive(Clone, Copy, Debug)]
+pub struct RandomRollout {
+    /// Number of rollouts per leaf.
+    pub rollouts: usize,
+    /// Plays the move solving the most tasks out of a few random moves, instead of any move.
+    pub guided: bool,
+}
+
+/// Number of random moves a guided rollout chooses from.
+const GUIDED_CANDIDATES: usize = 3;
+
+impl RandomRollout {
+    fn rollout(&self, state: &State, scoring: &Scoring, rng: &mut StdRng) -> f64 {
+        let mut state = state.clone();
+        loop {
+            let moves = state.available_moves();
+            let m = if self.guided {
+                moves
+                    .choose_multiple(rng, GUIDED_CANDIDATES)
+                    .max_by_key(|m| solved_tasks_after(&mut state, m))
+            } else {
+                moves.choose(rng)
+            };
+            match m {
+                Some(m) => state.make_move(m),
+                None => return scoring.score(&state),
+            }
+        }
+    }
+}
+
+fn solved_tasks_after(state: &mut State, m: &Move) -> usize {
+    state.make_move(m);
+    let solved = state.count_solved_tasks();
+    state.unmake_move();
+    solved
+}
+
+impl Default for RandomRollout {
+    fn default() -> RandomRollout {
+        RandomRollout {
+            rollouts: 1,
+            guided: false,
+        }
+    }
+}
+
+impl LeafEvaluator for RandomRollout {
+    fn evaluate(&self, state: &State, scoring: &Scoring, rng: &mut StdRng) -> f64 {
+        let total: f64 = (0..self.rollouts)
+            .map(|_| self.rollout(state, scoring, rng))
+            .sum();
+        total / self.rollouts.max(1) as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nova_luna::{Color, Position, Task, Tile};
+    use rand::SeedableRng;
+
+    #[test]
+    fn built_in_evaluators() {
+        let teal = Tile::new(1, Color::Teal, vec![]);
+        let blue = Tile::new(
+            1,
+            Color::Blue,
+            vec![Task::new(vec![Color::Teal, Color::Red])],
+        );
+        let red = Tile::new(1, Color::Red, vec![]);
+        let mut state = State::with_tiles(vec![teal.clone(), blue.clone(), red]);
+        for (tile, x) in [(&teal, 0), (&blue, 1)] {
+            let id = state.tile_id(tile).unwrap();
+            state.make_move(&Move::new(id, Position::new(x, 0)));
+        }
+        let mut rng = StdRng::seed_from_u64(0);
+        let scoring = Scoring::Tokens;
+
+        assert_eq!(0.0, SolvedTasks.evaluate(&state, &scoring, &mut rng));
+        assert_eq!(
+            0.25,
+            PartialProgress::default().evaluate(&state, &scoring, &mut rng)
+        );
+        // The red tile is placed next to the blue one in half of the positions.
+        let rollouts: Vec<f64> = (0..20)
+            .map(|_| RandomRollout::default().evaluate(&state, &scoring, &mut rng))
+            .collect();
+        assert!(rollouts.contains(&0.0));
+        assert!(rollouts.contains(&1.0));
+
+        let average = RandomRollout {
+            rollouts: 1000,
+            guided: false,
+        }
+        .evaluate(&state, &scoring, &mut rng);
+        assert!((0.4..0.6).contains(&average), "{}", average);
+        // Three of the six positions solve the task, so at least one of three random
+        // candidates does in most rollouts.
+        let guided = RandomRollout {
+            rollouts: 1000,
+            guided: true,
+        }
+        .evaluate(&state, &scoring, &mut rng);
+        assert!(guided > 0.8, "{}", guided);
+    }
+
+    #[test]
+    fn names() {
+        for name in NAMES {
+            assert!(by_name(name).is_ok());
+        }
+        assert!(by_name("rollout:8").is_ok());
+        assert!(by_name("minimax").is_err());
+        assert!(by_name("rollout:0").is_err());
+        assert!(by_name("solved:8").err().unwrap().contains("\"solved:8\""));
+        assert!(by_name("rollout:x")
+            .err()
+            .unwrap()
+            .contains("\"rollout:x\""));
+    }
+}
@@ -0,0 +1,276 @@
+//! Exact search of the best board for small sets of tiles.
+//!
+//! A depth-first branch and bound over the moves of a [`State`]: a board is only searched
+//! further if an upper bound of its final score beats the best complete board found so far.
+//! Boards reached by placing the same tiles on the same positions in a different order, as well as
+//! boards which are shifted, rotated or mirrored copies of each other, are looked up in a
+//! transposition table instead of being searched again.
+//!
+//! The number of boards grows so quickly with the number of tiles that the search only finishes
+//! in reasonable time for up to about eight tiles.
+
+use crate::nova_luna::{BoardKey, Move, State, TileBudget, MAX_TILES};
+use crate::scoring::Scoring;
+use mcts::GameState;
+use std::collections::HashMap;
+
+/// Best board of an exact search.
+#[derive(Clone, Debug)]
+pub struct ExactSolution {
+    /// Moves placing the remaining tiles of the searched state.
+    pub moves: Vec<Move>,
+    pub score: f64,
+    /// Number of boards the search visited.
+    pub nodes: u64,
+}
+
+struct Search<'a> {
+    scoring: &'a Scoring,
+    /// Tasks of each tile which can be solved at all with the tiles of the game, bit `i` for
+    /// task `i`.
+    solvable: Vec<u8>,
+    /// Upper bounds of the final scores reachable from the boards searched so far.
+    table: HashMap<BoardKey, f64>,
+    path: Vec<Move>,
+    best: Option<(f64, Vec<Move>)>,
+    nodes: u64,
+}
+
+/// Searches all ways to place the remaining tiles of the state, or to choose and place tiles
+/// within its budget, and returns a board with the highest score.
+pub fn solve_exact(state: &State, scoring: &Scoring) -> ExactSolution {
+    let mut search = Search {
+        scoring,
+        solvable: solvable_tasks(state),
+        table: HashMap::new(),
+        path: vec![],
+        best: None,
+        nodes: 0,
+    };
+    search.search(&mut state.clone());
+    let (score, moves) = search.best.expect("every state has a complete board");
+    ExactSolution {
+        moves,
+        score,
+        nodes: search.nodes,
+    }
+}
+
+/// Finds the tasks which require a color none of the other tiles of the game has, which no
+/// board can solve.
+fn solvable_tasks(state: &State) -> Vec<u8> {
+    let mut copies = [0; MAX_TILES];
+    for id in state.tile_ids() {
+        copies[id.index()] = state.available(id);
+    }
+    for (id, _) in state.placed_ids() {
+        copies[id.index()] += 1;
+    }
+    let mut colors = [0; 4];
+    for id in state.tile_ids() {
+        colors[state.tile(id).color() as usize] += copies[id.index()];
+    }
+
+    state
+        .tile_ids()
+        .map(|id| {
+            let tile = state.tile(id);
+            let mut others = colors;
+            others[tile.color() as usize] -= 1;
+            tile.tasks()
+                .iter()
+                .enumerate()
+                .filter(|(_, task)| task.colors().iter().all(|c| others[*c as usize] > 0))
+                .fold(0, |solvable, (t, _)| solvable | (1 << t))
+        })
+        .collect()
+}
+
+impl Search<'_> {
+    /// Returns an upper bound of the best final score reachable from the state, which is exact
+    /// if it beats the best score found before.
+    fn search(&mut self, state: &mut State) -> f64 {
+        self.nodes += 1;
+        if state.affordable_tiles().next().is_none() {
+            let score = self.scoring.score(state);
+            if self.best.as_ref().is_none_or(|(best, _)| score > *best) {
+                self.best = Some((score, self.path.clone()));
+            }
+            return score;
+        }
+
+        let mut moves = state.available_moves();
+        let key = state.canonical_key();
+        let bound = match self.table.get(&key) {
+            Some(bound) => *bound,
+            None => self.bound(state),
+        };
+        if self.is_pruned(bound) {
+            return bound;
+        }
+
+        // Trying the moves solving the most tasks first finds good boards early, which prunes
+        // more of the remaining moves. The last tile completes the board, so its moves are all
+        // tried anyway.
+        if state.remaining_tiles().nth(1).is_some() {
+            moves.sort_by_cached_key(|m| {
+                state.make_move(m);
+                let solved = state.count_solved_tasks();
+                state.unmake_move();
+                std::cmp::Reverse(solved)
+            });
+        }
+        let mut value = f64::NEG_INFINITY;
+        for m in moves {
+            state.make_move(&m);
+            self.path.push(m);
+            value = value.max(self.search(state));
+            self.path.pop();
+            state.unmake_move();
+        }
+        self.table.insert(key, value);
+        value
+    }
+
+    fn is_pruned(&self, bound: f64) -> bool {
+        matches!(&self.best, Some((best, _)) if bound <= *best)
+    }
+
+    /// Bounds the final score by assuming that every task which is not solved yet but can be
+    /// solved will be solved, by taking the tiles with the most of these tasks.
+    fn bound(&self, state: &State) -> f64 {
+        let placed: usize = state
+            .solvable_placed()
+            .map(|(id, solvable)| (solvable & self.solvable[id.index()]).count_ones() as usize)
+            .sum();
+        // Solvable tasks and cost of every tile which still can be taken.
+        let mut tiles: Vec<(usize, usize)> = state
+            .affordable_tiles()
+            .flat_map(|id| {
+                let tasks = self.solvable[id.index()].count_ones() as usize;
+                std::iter::repeat_n((tasks, state.tile(id).cost()), state.available(id))
+            })
+            .collect();
+        let spent = state.spent_cost();
+        let min_cost = spent + tiles.iter().map(|t| t.1).min().unwrap_or(0);
+
+        let (remaining, max_cost) = match state.budget() {
+            None => (
+                tiles.iter().map(|t| t.0).sum(),
+                spent + tiles.iter().map(|t| t.1).sum::<usize>(),
+            ),
+            Some(TileBudget::Count(n)) => {
+                let n = n.saturating_sub(state.placed_ids().count());
+                tiles.sort_unstable_by_key(|t| std::cmp::Reverse(t.0));
+                let tasks = tiles.iter().take(n).map(|t| t.0).sum();
+                tiles.sort_unstable_by_key(|t| std::cmp::Reverse(t.1));
+                (
+                    tasks,
+                    spent + tiles.iter().take(n).map(|t| t.1).sum::<usize>(),
+                )
+            }
+            Some(TileBudget::Cost(budget)) => {
+                // Taking fractions of tiles by their tasks per cost bounds the tasks of any
+                // choice of tiles within the budget.
+                tiles.sort_unstable_by(|a, b| (b.0 * a.1).cmp(&(a.0 * b.1)));
+                let mut left = budget - spent;
+                let mut tasks = 0;
+                for (t, cost) in &tiles {
+                    if *cost > left {
+                        tasks += t * left / cost;
+                        break;
+                    }
+                    tasks += t;
+                    left -= cost;
+                }
+                let all: usize = tiles.iter().map(|t| t.1).sum();
+                (tasks, budget.min(spent + all))
+            }
+        };
+
+        // For a fixed cost, the score is linear in the number of tokens and for a fixed number
+        // of tokens it is monotonic in the cost, so it is highest at a corner of the ranges.
+        let tokens = placed + remaining;
+        [
+            (0, min_cost),
+            (0, max_cost),
+            (tokens, min_cost),
+            (tokens, max_cost),
+        ]
+        .iter()
+        .map(|(tokens, cost)| self.scoring.score_of(*tokens, *cost))
+        .fold(f64::NEG_INFINITY, f64::max)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nova_luna::Tile;
+
+    /// Tries every sequence of moves without any pruning.
+    fn brute_force(state: &mut State, scoring: &Scoring) -> f64 {
+        let moves = state.available_moves();
+        if moves.is_empty() {
+            return scoring.score(state);
+        }
+        let mut best = f64::NEG_INFINITY;
+        for m in moves {
+            state.make_move(&m);
+            best = best.max(brute_force(state, scoring));
+            state.unmake_move();
+        }
+        best
+    }
+
+    fn tiles(n: usize) -> Vec<Tile> {
+        crate::parse_file("../resources/tiles.json").unwrap()[..n].to_vec()
+    }
+
+    #[test]
+    fn equals_brute_force() {
+        for n in 1..=4 {
+            for scoring in [Scoring::Tokens, Scoring::TokensPerCost] {
+                let state = State::with_tiles(tiles(n));
+                let solution = solve_exact(&state, &scoring);
+
+                let mut game = state.clone();
+                for m in &solution.moves {
+                    game.make_move(m);
+                }
+                assert!(game.available_moves().is_empty());
+                assert_eq!(solution.score, scoring.score(&game));
+                assert_eq!(brute_force(&mut state.clone(), &scoring), solution.score);
+            }
+        }
+    }
+
+    #[test]
+    fn continues_started_board() {
+        let mut state = State::with_tiles(tiles(5));
+        let first = state.available_moves()[0];
+        state.make_move(&first);
+        let solution = solve_exact(&state, &Scoring::Tokens);
+        assert_eq!(4, solution.moves.len());
+        assert_eq!(brute_force(&mut state, &Scoring::Tokens), solution.score);
+    }
+
+    #[test]
+    fn chooses_tiles_within_budget() {
+        for budget in [TileBudget::Count(3), TileBudget::Cost(8)] {
+            for scoring in [Scoring::Tokens, Scoring::TokensPerCost] {
+                let state = State::with_budget(tiles(6), budget);
+                let solution = solve_exact(&state, &scoring);
+
+                let mut game = state.clone();
+                for m in &solution.moves {
+                    game.make_move(m);
+                }
+                assert!(game.available_moves().is_empty());
+                assert!(game.remaining_tiles().count() > 0, "{}", budget);
+                assert_eq!(solution.score, scoring.score(&game));
+                assert_eq!(brute_force(&mut state.clone(), &scoring), solution.score);
+            }
+        }
+    }
+}
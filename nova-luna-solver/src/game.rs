@@ -0,0 +1,421 @@
+//! Game of two to four players, who buy tiles from a shared pool with steps on the moon track
+//! and place them on their own boards.
+//!
+//! Every solved task of a board places one token of its player. The first player placing all of
+//! their tokens wins. If no tiles are left, the player with the fewest remaining tokens wins.
+//!
+//! Without a [`Market`] any tile of the pool can be bought, with a market only the tiles next to
+//! the moon meeple.
+
+use crate::error::SolverError;
+use crate::market::Market;
+use crate::nova_luna::{Move, State, Tile, TileId, MAX_TILES};
+use mcts::GameState;
+use rand::Rng;
+use std::ops::RangeInclusive;
+
+/// Number of spaces of the circular moon track.
+pub const MOON_TRACK_LENGTH: usize = 24;
+/// Number of tokens every player has to place.
+pub const TOKENS: usize = 21;
+
+/// Index of a player in the turn order of the first round.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Player(u8);
+
+impl Player {
+    pub fn new(index: usize) -> Player {
+        Player(index as u8)
+    }
+
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Board and moon track marker of a player.
+#[derive(Clone, Debug)]
+pub struct PlayerState {
+    board: State,
+    moon: usize,
+    /// When the marker arrived on its space, the latest marker is on top of the stack.
+    arrival: usize,
+}
+
+impl PlayerState {
+    pub fn board(&self) -> &State {
+        &self.board
+    }
+
+    /// Returns the number of steps the player has moved on the moon track.
+    pub fn moon_steps(&self) -> usize {
+        self.moon
+    }
+
+    /// Returns the space of the marker on the moon track.
+    pub fn moon_position(&self) -> usize {
+        self.moon % MOON_TRACK_LENGTH
+    }
+}
+
+/// State of a game with several players.
+///
+/// The boards of all players share the tiles of the pool, so the [`TileId`]s of a move are the
+/// same for every player.
+#[derive(Clone, Debug)]
+pub struct Game {
+    players: Vec<PlayerState>,
+    pool: [u8; MAX_TILES],
+    tokens: usize,
+    arrivals: usize,
+    market: Option<Market>,
+}
+
+impl Game {
+    pub const PLAYERS_RANGE: RangeInclusive<usize> = 2..=4;
+
+    /// Starts a game with the tiles in the pool and all markers stacked on the first space of
+    /// the moon track, the first player on top.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of players is outside of [`Game::PLAYERS_RANGE`] or the tiles exceed
+    /// the limits of [`State::with_tiles`].
+    pub fn new(tiles: Vec<Tile>, num_players: usize) -> Game {
+        Game::with_tokens(tiles, num_players, TOKENS)
+    }
+
+    /// Starts a game in which every player has to place the given number of tokens.
+    pub fn with_tokens(tiles: Vec<Tile>, num_players: usize, tokens: usize) -> Game {
+        assert!(
+            Game::PLAYERS_RANGE.contains(&num_players),
+            "{} players (must be in {:?})",
+            num_players,
+            Game::PLAYERS_RANGE
+        );
+        let board = State::with_tiles(tiles);
+        let mut pool = [0; MAX_TILES];
+        for id in board.tile_ids() {
+            pool[id.index()] = board.available(id) as u8;
+        }
+        let players = (0..num_players)
+            .map(|idx| PlayerState {
+                board: board.clone(),
+                moon: 0,
+                arrival: num_players - idx,
+            })
+            .collect();
+        Game {
+            players,
+            pool,
+            tokens,
+            arrivals: num_players + 1,
+            market: None,
+        }
+    }
+
+    /// Starts a game in which the tiles are shuffled into the draw pile of a market.
+    pub fn with_market<R: Rng>(tiles: Vec<Tile>, num_players: usize, rng: &mut R) -> Game {
+        let mut game = Game::new(tiles, num_players);
+        let board = &game.players[0].board;
+        let tiles = board
+            .tile_ids()
+            .flat_map(|id| std::iter::repeat_n(id, board.available(id)))
+            .collect();
+        game.market = Some(Market::new(tiles, rng));
+        game
+    }
+
+    pub fn market(&self) -> Option<&Market> {
+        self.market.as_ref()
+    }
+
+    /// Replaces the draw pile of the market by the tiles, the next tile to draw being the last
+    /// one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the game has no market.
+    pub fn set_draw_pile(&mut self, tiles: &[Tile]) -> Result<(), SolverError> {
+        let market = self.market.as_ref().expect("game has no market");
+        // The copies the game started with, less the copies bought by any of the players.
+        let first = &self.players[0].board;
+        let mut left = [0; MAX_TILES];
+        for id in first.tile_ids() {
+            left[id.index()] = first.available(id);
+        }
+        for (id, _) in first.placed_ids() {
+            left[id.index()] += 1;
+        }
+        for player in &self.players {
+            for (id, _) in player.board.placed_ids() {
+                left[id.index()] -= 1;
+            }
+        }
+
+        let mut pool = [0; MAX_TILES];
+        for purchase in market.tiles() {
+            pool[purchase.tile.index()] += 1;
+        }
+        let mut draw_pile = Vec::with_capacity(tiles.len());
+        for (idx, tile) in tiles.iter().enumerate() {
+            let id = self.players[0].board.tile_id(tile);
+            match id {
+                Some(id) if (pool[id.index()] as usize) < left[id.index()] => {
+                    pool[id.index()] += 1;
+                    draw_pile.push(id);
+                }
+                _ => return Err(SolverError::UnavailableTile { tile: idx }),
+            }
+        }
+        self.pool = pool;
+        self.market
+            .as_mut()
+            .expect("game has no market")
+            .set_draw_pile(draw_pile);
+        Ok(())
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn player(&self, player: Player) -> &PlayerState {
+        &self.players[player.index()]
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = Player> {
+        (0..self.players.len()).map(Player::new)
+    }
+
+    /// Returns the number of tokens every player has to place.
+    pub fn tokens(&self) -> usize {
+        self.tokens
+    }
+
+    /// Returns how many tokens the player still has to place.
+    pub fn tokens_left(&self, player: Player) -> usize {
+        self.tokens
+            .saturating_sub(self.player(player).board.count_solved_tasks())
+    }
+
+    /// Returns how many tiles of the ID are left in the pool, including the market and its draw
+    /// pile.
+    pub fn in_pool(&self, id: TileId) -> usize {
+        self.pool.get(id.index()).copied().unwrap_or(0) as usize
+    }
+
+    /// Returns whether the next player can buy a tile of the ID.
+    pub fn can_buy(&self, id: TileId) -> bool {
+        match &self.market {
+            Some(market) => market.purchases().iter().any(|p| p.tile == id),
+            None => self.in_pool(id) > 0,
+        }
+    }
+
+    /// Returns the rearmost player on the moon track, who is the next to move. Of several
+    /// players on the same space the one on top of the stack moves first.
+    pub fn next_player(&self) -> Player {
+        let (idx, _) = self
+            .players
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, p)| (p.moon, std::cmp::Reverse(p.arrival)))
+            .expect("a game has players");
+        Player::new(idx)
+    }
+
+    /// The game is over if a player has placed all tokens or the pool is empty.
+    pub fn is_over(&self) -> bool {
+        self.players().any(|p| self.tokens_left(p) == 0) || self.pool.iter().all(|n| *n == 0)
+    }
+
+    /// Returns the players with the fewest remaining tokens once the game is over.
+    pub fn winners(&self) -> Vec<Player> {
+        if !self.is_over() {
+            return vec![];
+        }
+        let fewest = self.players().map(|p| self.tokens_left(p)).min();
+        self.players()
+            .filter(|p| Some(self.tokens_left(*p)) == fewest)
+            .collect()
+    }
+
+    /// Buys the tile of the move for the next player, places it on their board and moves their
+    /// marker by the cost of the tile.
+    ///
+    /// Of two identical tiles in the market, the one nearer to the moon meeple is bought.
+    pub fn try_make_move(&mut self, mov: &Move) -> Result<(), SolverError> {
+        let player = self.next_player().index();
+        if self.is_over() || !self.can_buy(mov.tile()) {
+            return Err(self.players[player].board.illegal_move(mov));
+        }
+        let state = &mut self.players[player];
+        state.board.try_make_move(mov)?;
+        state.moon += state.board.tile(mov.tile()).cost();
+        state.arrival = self.arrivals;
+        self.arrivals += 1;
+        self.pool[mov.tile().index()] -= 1;
+        if let Some(market) = &mut self.market {
+            market.buy_tile(mov.tile());
+        }
+        Ok(())
+    }
+}
+
+impl GameState for Game {
+    type Move = Move;
+    type Player = Player;
+    type MoveList = Vec<Move>;
+
+    fn current_player(&self) -> Player {
+        self.next_player()
+    }
+
+    fn available_moves(&self) -> Vec<Move> {
+        if self.is_over() {
+            return vec![];
+        }
+        let board = &self.player(self.next_player()).board;
+        let positions = board.free_positions();
+        board
+            .tile_ids()
+            .filter(|id| self.can_buy(*id))
+            .flat_map(|id| positions.iter().map(move |pos| Move::new(id, *pos)))
+            .collect()
+    }
+
+    fn make_move(&mut self, mov: &Move) {
+        self.try_make_move(mov)
+            .expect("search must only play available moves");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nova_luna::{Color, Position, Task};
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    fn tile(cost: usize) -> Tile {
+        Tile::new(cost, Color::Blue, vec![Task::new(vec![Color::Blue])])
+    }
+
+    fn buy(game: &mut Game, cost: usize, x: isize) {
+        let id = game.players[0].board.tile_id(&tile(cost)).unwrap();
+        game.make_move(&Move::new(id, Position::new(x, 0)));
+    }
+
+    #[test]
+    fn rearmost_player_moves() {
+        let mut game = Game::new(vec![tile(1), tile(2), tile(3), tile(3)], 3);
+        assert_eq!(Player(0), game.current_player());
+
+        buy(&mut game, 3, 0);
+        assert_eq!(Player(1), game.current_player());
+        buy(&mut game, 2, 0);
+        assert_eq!(Player(2), game.current_player());
+        buy(&mut game, 3, 0);
+        // Player 1 is the rearmost player.
+        assert_eq!(Player(1), game.current_player());
+        buy(&mut game, 1, 1);
+        // Player 1 arrived last on the space of players 0 and 2.
+        assert_eq!(Player(1), game.current_player());
+        assert_eq!(3, game.player(Player(1)).moon_position());
+        assert_eq!(2, game.player(Player(1)).board().count_solved_tasks());
+        assert_eq!(0, game.player(Player(2)).board().count_solved_tasks());
+    }
+
+    #[test]
+    fn game_ends_with_empty_pool() {
+        let mut game = Game::new(vec![tile(1), tile(2)], 2);
+        assert!(game.winners().is_empty());
+        buy(&mut game, 1, 0);
+        let id = game.players[0].board.tile_id(&tile(1)).unwrap();
+        assert!(game
+            .try_make_move(&Move::new(id, Position::new(0, 0)))
+            .is_err());
+        buy(&mut game, 2, 0);
+
+        assert!(game.is_over());
+        assert!(game.available_moves().is_empty());
+        assert_eq!(vec![Player(0), Player(1)], game.winners());
+    }
+
+    #[test]
+    fn game_ends_with_all_tokens_placed() {
+        let mut game = Game::with_tokens(vec![tile(1); 4], 2, 2);
+        buy(&mut game, 1, 0);
+        buy(&mut game, 1, 0);
+        // Player 1 is on top of player 0 and moves again.
+        buy(&mut game, 1, 1);
+        assert_eq!(0, game.tokens_left(Player(1)));
+        assert!(game.is_over());
+        assert!(game.available_moves().is_empty());
+        assert_eq!(vec![Player(1)], game.winners());
+    }
+
+    #[test]
+    fn random_games() {
+        let tiles = crate::parse_file("../resources/tiles.json").unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+        for num_players in Game::PLAYERS_RANGE {
+            let mut game = Game::new(tiles.clone(), num_players);
+            while let Some(m) = game.available_moves().choose(&mut rng) {
+                game.make_move(m);
+            }
+            let placed: usize = game
+                .players()
+                .map(|p| game.player(p).board().placed_tiles().count())
+                .sum();
+            assert_eq!(tiles.len(), placed);
+            assert!(!game.winners().is_empty());
+        }
+    }
+
+    #[test]
+    fn market_restricts_purchases() {
+        let tiles: Vec<Tile> = (1..=7).chain(1..=7).map(tile).collect();
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut game = Game::with_market(tiles, 2, &mut rng);
+        while !game.is_over() {
+            let market = game.market().unwrap();
+            let purchasable: Vec<TileId> = market.purchases().iter().map(|p| p.tile).collect();
+            let moves = game.available_moves();
+            assert!(moves.iter().all(|m| purchasable.contains(&m.tile())));
+
+            let board = game.player(game.next_player()).board();
+            let position = board.free_positions()[0];
+            for id in board.tile_ids().filter(|id| !purchasable.contains(id)) {
+                assert!(game
+                    .clone()
+                    .try_make_move(&Move::new(id, position))
+                    .is_err());
+            }
+            game.make_move(moves.choose(&mut rng).unwrap());
+        }
+        assert!(game.market().unwrap().is_empty());
+    }
+
+    #[test]
+    fn draw_pile_excludes_bought_tiles() {
+        let mut game = Game::new(vec![tile(1), tile(1), tile(2), tile(2)], 2);
+        buy(&mut game, 1, 0);
+        buy(&mut game, 1, 0);
+        game.market = Some(Market::with_draw_pile(vec![]));
+
+        // Both copies of the first tile are on the boards of the players.
+        assert!(matches!(
+            game.set_draw_pile(&[tile(1)]),
+            Err(SolverError::UnavailableTile { tile: 0 })
+        ));
+        assert!(game.set_draw_pile(&[tile(2), tile(2), tile(2)]).is_err());
+        assert!(game.set_draw_pile(&[tile(2), tile(2)]).is_ok());
+        assert_eq!(
+            2,
+            game.in_pool(game.players[0].board.tile_id(&tile(2)).unwrap())
+        );
+    }
+}
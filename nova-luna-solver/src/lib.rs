@@ -1,116 +1,494 @@
+mod board;
+pub mod catalog;
+mod components;
+mod error;
+pub mod evaluator;
+mod exact;
+pub mod game;
+pub mod market;
+pub mod multiplayer;
 pub mod nova_luna;
+mod recommend;
+mod saved;
+mod scoring;
 mod solver;
+mod validation;
+mod verify;
+mod zobrist;
 
-use crate::nova_luna::{State, Tile};
+use crate::catalog::TileRef;
+pub use crate::error::SolverError;
+use crate::evaluator::LeafEvaluator;
+pub use crate::exact::{solve_exact, ExactSolution};
+use crate::nova_luna::Move;
+use crate::nova_luna::{State, Tile, TileBudget};
+pub use crate::recommend::{
+    board_state, parse_position, recommend, RecommendOptions, Recommendation,
+};
+pub use crate::saved::{load_state, save_state, SavedState, SavedTile, SCHEMA_VERSION};
+pub use crate::scoring::Scoring;
 use crate::solver::NovaLunaBoardGameMCTS;
+use crate::solver::SearchState;
+use crate::solver::SeededUCTPolicy;
 use crate::solver::StateEvaluator;
+pub use crate::solver::TableStatistics;
+use crate::solver::ThreadSeeds;
+use crate::solver::{SearchTable, TableCounters};
+pub use crate::validation::{validate_tiles, Diagnostic, Problem};
+pub use crate::verify::{verify_board, verify_string, Discrepancy, Inconsistency};
 use chrono::Utc;
-use mcts::transposition_table::*;
-use mcts::tree_policy::*;
 use mcts::*;
 use serde::Serialize;
+use std::fmt;
 use std::io::Write;
 use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+#[derive(Clone)]
 pub struct SolverParameters<'a> {
     pub tiles: Vec<Tile>,
+    /// Treats the tiles as a pool and takes tiles until no further tile fits into the budget.
+    /// The search chooses the tiles along with their positions.
+    pub budget: Option<TileBudget>,
     pub output_file: Option<&'a str>,
     pub output_dir: Option<&'a str>,
     pub print_statistics: bool,
     pub print_moves: bool,
     pub num_playouts: u32,
+    /// Stops the search at the time limit instead of after `num_playouts`.
+    pub time_limit: Option<Duration>,
     pub num_threads: usize,
     pub debug: bool,
     pub exploration_constant: f64,
+    /// Estimates the value of the boards at the leaves of the search tree.
+    pub evaluator: Arc<dyn LeafEvaluator>,
+    pub scoring: Scoring,
+    pub strategy: Strategy,
+    /// Transposition table of the MCTS search.
+    pub transpositions: Transpositions,
+    /// Makes the search deterministic. Each thread searches its own tree, seeded with the seed
+    /// plus the index of the thread, and the best result of all threads is returned.
+    pub seed: Option<u64>,
+}
+
+/// Search used to place the tiles.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// Monte Carlo tree search, which handles any number of tiles within the playouts or time
+    /// limit.
+    #[default]
+    Mcts,
+    /// Exhaustive search of all boards, which finds the best board but is only feasible for up
+    /// to about eight tiles.
+    Exact,
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Strategy, String> {
+        match s {
+            "mcts" => Ok(Strategy::Mcts),
+            "exact" => Ok(Strategy::Exact),
+            _ => Err(format!("unknown strategy \"{}\"", s)),
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strategy::Mcts => write!(f, "mcts"),
+            Strategy::Exact => write!(f, "exact"),
+        }
+    }
+}
+
+/// Transposition table of the MCTS search, which lets boards reached by different orders of
+/// moves, or shifted copies of them, share a node of the search tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transpositions {
+    /// Every order of moves gets its own nodes.
+    Off,
+    /// Hash table with the number of entries, or by default with enough entries for a node of
+    /// each of the `num_playouts` playouts, also if the search stops at a time limit. Once the
+    /// table is filled by two thirds, further boards get their own nodes. Boards with the same
+    /// hash are told apart by their placed tiles, and only the first of them is stored.
+    Approx(Option<usize>),
+    /// Map of the placed tiles of the boards, which never confuses two boards but grows with the
+    /// search tree.
+    Exact,
+}
+
+impl Default for Transpositions {
+    fn default() -> Transpositions {
+        Transpositions::Approx(None)
+    }
+}
+
+impl FromStr for Transpositions {
+    type Err = String;
+
+    /// Parses "off", "exact", "approx" or "approx:<entries>".
+    fn from_str(s: &str) -> Result<Transpositions, String> {
+        match s.split_once(':') {
+            None if s == "off" => Ok(Transpositions::Off),
+            None if s == "exact" => Ok(Transpositions::Exact),
+            None if s == "approx" => Ok(Transpositions::Approx(None)),
+            Some(("approx", entries)) => entries
+                .parse()
+                .map(|entries| Transpositions::Approx(Some(entries)))
+                .map_err(|_| format!("invalid number of entries in \"{}\"", s)),
+            _ => Err(format!("unknown transposition table \"{}\"", s)),
+        }
+    }
+}
+
+impl fmt::Display for Transpositions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transpositions::Off => write!(f, "off"),
+            Transpositions::Approx(None) => write!(f, "approx"),
+            Transpositions::Approx(Some(entries)) => write!(f, "approx:{}", entries),
+            Transpositions::Exact => write!(f, "exact"),
+        }
+    }
 }
 
 #[derive(Serialize)]
 struct Statistics {
     solved_tasks: usize,
+    score: f64,
+    /// Sum of the moon track costs of the placed tiles.
+    cost: usize,
     duration: Duration,
+    /// Use of the transposition table, if the tiles were placed by MCTS.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transpositions: Option<TableStatistics>,
+}
+
+/// Parses a JSON list of tiles, which are given either as objects or by their ID in the
+/// [catalog](crate::catalog).
+pub fn parse_string(input: String) -> Result<Vec<Tile>, SolverError> {
+    let tiles: Vec<TileRef> = serde_json::from_str(&input)?;
+    let tiles = catalog::resolve(tiles)?;
+    validation::check_schema(&tiles)?;
+    Ok(tiles)
 }
 
-pub fn parse_string(input: String) -> Vec<Tile> {
-    serde_json::from_str(&input).expect("cannot parse tiles")
+/// Parses the tiles without rejecting invalid values and reports all problems of the tiles.
+pub fn validate_string(input: String) -> Result<Vec<Diagnostic>, SolverError> {
+    let tiles: Vec<TileRef> = serde_json::from_str(&input)?;
+    let tiles = catalog::resolve(tiles)?;
+    Ok(validate_tiles(&tiles))
 }
 
-pub fn parse_file<P: AsRef<Path>>(path: P) -> Vec<Tile> {
-    let unplaced_tiles = std::fs::read_to_string(path).expect("cannot read file");
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Vec<Tile>, SolverError> {
+    let unplaced_tiles = std::fs::read_to_string(path)?;
     parse_string(unplaced_tiles)
 }
 
-pub fn solve(param: SolverParameters) -> State {
+pub fn solve(param: SolverParameters) -> Result<State, SolverError> {
+    validation::check_schema(&param.tiles)?;
+    resume(initial_state(&param), param)
+}
+
+/// Returns the empty board of the tiles of the parameters, which are a pool if there is a
+/// budget.
+fn initial_state(param: &SolverParameters) -> State {
+    let tiles = param.tiles.clone();
+    match param.budget {
+        Some(budget) => State::with_budget(tiles, budget),
+        None => State::with_tiles(tiles),
+    }
+}
+
+/// Continues a game, e.g. one loaded by [`load_state`], placing its remaining tiles. The tiles
+/// of the parameters are ignored.
+pub fn resume(state: State, param: SolverParameters) -> Result<State, SolverError> {
     let now = Instant::now();
-    let num_tiles = param.tiles.len();
 
-    let state = State::with_tiles(param.tiles.clone());
-    let mut mcts = MCTSManager::new(
-        state.clone(),
-        NovaLunaBoardGameMCTS,
-        StateEvaluator,
-        UCTPolicy::new(param.exploration_constant),
-        ApproxTable::new(1024),
-    );
+    let (moves, transpositions) = match param.strategy {
+        Strategy::Mcts => {
+            let (moves, transpositions) = search_mcts(&param, &state, now);
+            (moves, Some(transpositions))
+        }
+        Strategy::Exact => {
+            if param.print_statistics {
+                eprintln!(
+                    "# Exact\nSearching all boards of {} tiles.",
+                    state.remaining_tiles().count()
+                );
+            }
+            (solve_exact(&state, &param.scoring).moves, None)
+        }
+    };
+
+    let game = playout_best_moves(&param, state, moves)?;
+    print_statistics(&param, &now, &game, transpositions)?;
+    Ok(game)
+}
+
+/// Searches the moves with MCTS within the playouts or time limit of the parameters.
+fn search_mcts(
+    param: &SolverParameters,
+    state: &State,
+    now: Instant,
+) -> (Vec<Move>, TableStatistics) {
+    let budget = match param.time_limit {
+        Some(limit) => Budget::Deadline(now + limit),
+        None => Budget::Playouts(param.num_playouts),
+    };
+
+    if param.print_statistics {
+        match param.time_limit {
+            Some(limit) => eprintln!(
+                "# MCTS\nSearching for {:?} with {} threads.",
+                limit, param.num_threads
+            ),
+            None => eprintln!(
+                "# MCTS\nDoing {} playouts with {} threads.",
+                param.num_playouts, param.num_threads
+            ),
+        }
+    }
+
+    match param.seed {
+        None => search(param, state, rand::random(), budget, param.num_threads),
+        Some(seed) => search_seeded(param, state, seed, budget),
+    }
+}
+
+/// Search which can be continued as long as time permits and always knows the best moves
+/// found so far.
+pub struct AnytimeSearch {
+    mcts: MCTSManager<NovaLunaBoardGameMCTS>,
+    state: State,
+    num_threads: usize,
+    table: Arc<TableCounters>,
+}
+
+impl AnytimeSearch {
+    /// Starts a search of the tiles with the exploration constant, seed and threads of the
+    /// parameters.
+    pub fn new(param: &SolverParameters) -> AnytimeSearch {
+        AnytimeSearch::with_seed(
+            param,
+            initial_state(param),
+            param.seed.unwrap_or_else(rand::random),
+            param.num_threads,
+        )
+    }
 
-    eprintln!(
-        "# MCTS\nDoing {} playouts with {} threads.",
-        param.num_playouts, param.num_threads
-    );
+    fn with_seed(
+        param: &SolverParameters,
+        state: State,
+        seed: u64,
+        num_threads: usize,
+    ) -> AnytimeSearch {
+        // Every playout adds at most one node to the tree.
+        let table = SearchTable::new(param.transpositions, param.num_playouts as usize);
+        let counters = table.counters();
+        let mcts = MCTSManager::new(
+            SearchState(state.clone()),
+            NovaLunaBoardGameMCTS,
+            StateEvaluator {
+                evaluator: param.evaluator.clone(),
+                scoring: param.scoring,
+                // Decorrelates the evaluator from the tree policy seeded with the same seed.
+                seeds: ThreadSeeds::new(!seed),
+            },
+            SeededUCTPolicy::new(param.exploration_constant, seed),
+            table,
+        );
+        AnytimeSearch {
+            mcts,
+            state,
+            num_threads: num_threads.max(1),
+            table: counters,
+        }
+    }
 
-    mcts.playout_n_parallel(param.num_playouts, param.num_threads);
+    pub fn run_playouts(&mut self, num_playouts: u32) {
+        self.mcts.playout_n_parallel(num_playouts, self.num_threads);
+    }
+
+    /// Continues the search until the deadline has passed.
+    pub fn run_until(&mut self, deadline: Instant) {
+        if self.num_threads == 1 {
+            self.mcts.playout_until(|| Instant::now() >= deadline);
+        } else {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            self.mcts.playout_parallel_for(remaining, self.num_threads);
+        }
+    }
+
+    /// Returns the moves of the best game found so far.
+    pub fn best_moves(&self) -> Vec<Move> {
+        let mut game = SearchState(self.state.clone());
+        self.mcts
+            .principal_variation(self.state.remaining_tiles().count())
+            .iter()
+            .map(|m| {
+                let mov = game.absolute(m);
+                game.make_move(m);
+                mov
+            })
+            .collect()
+    }
+
+    /// Returns the lookups, hits and collisions of the transposition table so far.
+    pub fn table_statistics(&self) -> TableStatistics {
+        self.table.statistics()
+    }
+
+    /// Returns the board of the best game found so far.
+    pub fn best(&self) -> State {
+        let mut game = self.state.clone();
+        for m in self.best_moves() {
+            game.make_move(&m);
+        }
+        game
+    }
+}
+
+/// Criterion to stop a search.
+#[derive(Clone, Copy)]
+enum Budget {
+    Playouts(u32),
+    Deadline(Instant),
+}
+
+/// Searches a single tree with all threads and returns the principal variation.
+fn search(
+    param: &SolverParameters,
+    state: &State,
+    seed: u64,
+    budget: Budget,
+    num_threads: usize,
+) -> (Vec<Move>, TableStatistics) {
+    let mut search = AnytimeSearch::with_seed(param, state.clone(), seed, num_threads);
+    match budget {
+        Budget::Playouts(num_playouts) => search.run_playouts(num_playouts),
+        Budget::Deadline(deadline) => search.run_until(deadline),
+    }
 
     if param.debug {
         eprintln!("# Debug Moves");
-        mcts.tree().debug_moves();
+        search.mcts.tree().debug_moves();
     }
 
-    let game = playout_best_moves(&param, num_tiles, state, &mut mcts);
-    print_statistics(&param, &now, &game);
-    game
+    (search.best_moves(), search.table_statistics())
+}
+
+/// Searches a separate tree in each thread, so the result only depends on the seed, and returns
+/// the principal variation with the best score, along with the statistics of all tables.
+fn search_seeded(
+    param: &SolverParameters,
+    state: &State,
+    seed: u64,
+    budget: Budget,
+) -> (Vec<Move>, TableStatistics) {
+    let num_threads = param.num_threads.max(1) as u32;
+    let results: Vec<(Vec<Move>, TableStatistics)> = std::thread::scope(|scope| {
+        let threads: Vec<_> = (0..num_threads)
+            .map(|thread| {
+                let budget = match budget {
+                    Budget::Playouts(n) => {
+                        Budget::Playouts(n / num_threads + u32::from(thread < n % num_threads))
+                    }
+                    deadline => deadline,
+                };
+                let seed = seed.wrapping_add(thread as u64);
+                scope.spawn(move || search(param, state, seed, budget, 1))
+            })
+            .collect();
+        threads
+            .into_iter()
+            .map(|t| t.join().expect("search thread panicked"))
+            .collect()
+    });
+
+    let score = |moves: &Vec<Move>| {
+        let mut game = state.clone();
+        for m in moves {
+            game.make_move(m);
+        }
+        param.scoring.score(&game)
+    };
+    let mut results = results.into_iter();
+    let first = results.next().unwrap_or_default();
+    results.fold(first, |(best, table), (moves, statistics)| {
+        let table = table.combine(statistics);
+        if score(&moves) > score(&best) {
+            (moves, table)
+        } else {
+            (best, table)
+        }
+    })
 }
 
 fn playout_best_moves(
     param: &SolverParameters,
-    num_tiles: usize,
     state: State,
-    mcts: &mut MCTSManager<NovaLunaBoardGameMCTS>,
-) -> State {
+    moves: Vec<Move>,
+) -> Result<State, SolverError> {
     if param.print_moves {
         println!("# Moves:");
     }
     let mut game = state;
-    for m in mcts.principal_variation(num_tiles) {
-        game.make_move(&m);
+    for m in moves {
         if param.print_moves {
-            println!(
-                "{}",
-                serde_json::to_string(&m).expect("cannot serialize move")
-            );
+            println!("{}", serde_json::to_string(&game.placed_tile(&m))?);
         }
+        game.try_make_move(&m)?;
     }
-    game
+    Ok(game)
 }
 
-fn print_statistics(param: &SolverParameters, now: &Instant, game: &State) {
+/// Returns the tiles taken from the pool in the order they were placed, without the solved
+/// flags of the board.
+pub fn chosen_tiles(game: &State) -> Vec<Tile> {
+    game.board_tiles()
+        .into_iter()
+        .map(|(_, tile)| tile.with_solved_flags(0))
+        .collect()
+}
+
+fn print_statistics(
+    param: &SolverParameters,
+    now: &Instant,
+    game: &State,
+    transpositions: Option<TableStatistics>,
+) -> Result<(), SolverError> {
     if param.print_statistics {
         println!(
             "# Statistics:\n{}",
             serde_json::to_string(&Statistics {
                 solved_tasks: game.count_solved_tasks(),
-                duration: now.elapsed()
-            })
-            .expect("cannot print statistics")
+                score: param.scoring.score(game),
+                cost: game.spent_cost(),
+                duration: now.elapsed(),
+                transpositions,
+            })?
         );
     }
+    Ok(())
 }
 
-pub fn game_state_as_json(game: &State) -> String {
-    serde_json::to_string(&game).expect("cannot serialize game state")
+pub fn game_state_as_json(game: &State) -> Result<String, SolverError> {
+    Ok(serde_json::to_string(&game)?)
 }
 
-pub fn output_game_state(param: &SolverParameters, game: &State) {
-    let game_json = game_state_as_json(game);
+/// Writes the game as [versioned document](SavedState), which can be loaded by [`load_state`],
+/// into the output file of the parameters or to stdout.
+///
+/// Before the saved states were introduced, this wrote the plain JSON of the [`State`], which is
+/// still returned by [`game_state_as_json`].
+pub fn output_game_state(param: &SolverParameters, game: &State) -> Result<(), SolverError> {
+    let game_json = save_state(game)?;
 
     let mut write_to_std = true;
     if let Some(dir) = param.output_dir {
@@ -122,9 +500,8 @@ pub fn output_game_state(param: &SolverParameters, game: &State) {
 
             let path = Path::new(dir).join(file);
             eprintln!("writing board to \"{}\"", path.to_string_lossy());
-            let mut file = std::fs::File::create(path).expect("cannot create file");
-            file.write_all(game_json.as_bytes())
-                .expect("cannot write file");
+            let mut file = std::fs::File::create(path)?;
+            file.write_all(game_json.as_bytes())?;
             write_to_std = false;
         }
     }
@@ -132,4 +509,268 @@ pub fn output_game_state(param: &SolverParameters, game: &State) {
     if write_to_std {
         println!("# Game board\n{}", game_json);
     }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            parse_string("[{".to_string()),
+            Err(SolverError::Syntax(_))
+        ));
+        assert!(matches!(
+            parse_string(r#"[{"color": "Green", "cost": 1, "tasks": []}]"#.to_string()),
+            Err(SolverError::Schema(_))
+        ));
+        assert!(matches!(
+            parse_string(r#"[{"color": "Red", "cost": 0, "tasks": []}]"#.to_string()),
+            Err(SolverError::CostOutOfRange { tile: 0, cost: 0 })
+        ));
+        assert!(matches!(
+            parse_file("does-not-exist.json"),
+            Err(SolverError::Io(_))
+        ));
+        assert!(parse_file("../resources/tiles.json").is_ok());
+    }
+
+    fn solve_seeded(num_threads: usize, seed: u64, evaluator: &str) -> String {
+        let state = solve(SolverParameters {
+            tiles: parse_file("../resources/tiles.json").unwrap(),
+            budget: None,
+            output_file: None,
+            output_dir: None,
+            print_statistics: false,
+            print_moves: false,
+            num_playouts: 500,
+            time_limit: None,
+            num_threads,
+            debug: false,
+            exploration_constant: 2.0,
+            evaluator: evaluator::by_name(evaluator).unwrap(),
+            scoring: Scoring::Tokens,
+            strategy: Strategy::Mcts,
+            transpositions: Transpositions::default(),
+            seed: Some(seed),
+        })
+        .unwrap();
+        game_state_as_json(&state).unwrap()
+    }
+
+    #[test]
+    fn time_limited_search() {
+        let param = SolverParameters {
+            tiles: parse_file("../resources/tiles.json").unwrap(),
+            budget: None,
+            output_file: None,
+            output_dir: None,
+            print_statistics: false,
+            print_moves: false,
+            num_playouts: 0,
+            time_limit: Some(Duration::from_millis(200)),
+            num_threads: 2,
+            debug: false,
+            exploration_constant: 2.0,
+            evaluator: Arc::new(evaluator::SolvedTasks),
+            scoring: Scoring::Tokens,
+            strategy: Strategy::Mcts,
+            transpositions: Transpositions::default(),
+            seed: None,
+        };
+
+        let mut search = AnytimeSearch::new(&param);
+        search.run_until(Instant::now() + Duration::from_millis(50));
+        let first = search.best_moves();
+        search.run_until(Instant::now() + Duration::from_millis(50));
+        assert!(!first.is_empty());
+        assert!(!search.best_moves().is_empty());
+
+        let now = Instant::now();
+        let state = solve(param).unwrap();
+        assert!(now.elapsed() < Duration::from_secs(2));
+        assert!(state.placed_tiles().count() > 0);
+    }
+
+    #[test]
+    fn seeded_solve_is_reproducible() {
+        for evaluator in evaluator::NAMES {
+            assert_eq!(solve_seeded(1, 7, evaluator), solve_seeded(1, 7, evaluator));
+            assert_eq!(solve_seeded(3, 7, evaluator), solve_seeded(3, 7, evaluator));
+        }
+    }
+
+    #[test]
+    fn mcts_does_not_beat_exact_search() {
+        let tiles = parse_file("../resources/tiles.json").unwrap()[1..7].to_vec();
+        let param = |strategy, num_playouts, seed| SolverParameters {
+            tiles: tiles.clone(),
+            budget: None,
+            output_file: None,
+            output_dir: None,
+            print_statistics: false,
+            print_moves: false,
+            num_playouts,
+            time_limit: None,
+            num_threads: 1,
+            debug: false,
+            exploration_constant: 2.0,
+            evaluator: Arc::new(evaluator::SolvedTasks),
+            scoring: Scoring::Tokens,
+            strategy,
+            transpositions: Transpositions::default(),
+            seed: Some(seed),
+        };
+
+        let optimum = solve_exact(&State::with_tiles(tiles.clone()), &Scoring::Tokens).score;
+        let exact = solve(param(Strategy::Exact, 0, 0)).unwrap();
+        assert_eq!(optimum, Scoring::Tokens.score(&exact));
+
+        let scores: Vec<f64> = [100, 10000]
+            .iter()
+            .map(|n| Scoring::Tokens.score(&solve(param(Strategy::Mcts, *n, 5)).unwrap()))
+            .collect();
+        assert!(scores.iter().all(|s| *s <= optimum), "{:?}", scores);
+        assert_eq!(optimum, scores[1], "enough playouts find the optimum");
+    }
+
+    #[test]
+    fn selects_tiles_from_pool() {
+        let tiles = parse_file("../resources/tiles.json").unwrap();
+        for budget in [TileBudget::Count(4), TileBudget::Cost(12)] {
+            let pool = State::with_budget(tiles.clone(), budget);
+            let optimum = solve_exact(&pool, &Scoring::Tokens).score;
+            for strategy in [Strategy::Mcts, Strategy::Exact] {
+                let game = solve(SolverParameters {
+                    tiles: tiles.clone(),
+                    budget: Some(budget),
+                    output_file: None,
+                    output_dir: None,
+                    print_statistics: false,
+                    print_moves: false,
+                    num_playouts: 2000,
+                    time_limit: None,
+                    num_threads: 1,
+                    debug: false,
+                    exploration_constant: 2.0,
+                    evaluator: Arc::new(evaluator::SolvedTasks),
+                    scoring: Scoring::Tokens,
+                    strategy,
+                    transpositions: Transpositions::default(),
+                    seed: Some(3),
+                })
+                .unwrap();
+
+                match budget {
+                    TileBudget::Count(n) => assert_eq!(n, game.placed_tiles().count()),
+                    TileBudget::Cost(cost) => assert!(game.spent_cost() <= cost),
+                }
+                assert!(game.available_moves().is_empty(), "{} {}", budget, strategy);
+                assert_eq!(game.placed_tiles().count(), chosen_tiles(&game).len());
+                let score = Scoring::Tokens.score(&game);
+                assert!(score <= optimum);
+                if strategy == Strategy::Exact {
+                    assert_eq!(optimum, score);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn solves_full_catalog() {
+        let tiles: Vec<Tile> = catalog::catalog().iter().map(|t| t.tile.clone()).collect();
+        let state = solve(SolverParameters {
+            tiles: tiles.clone(),
+            budget: None,
+            output_file: None,
+            output_dir: None,
+            print_statistics: false,
+            print_moves: false,
+            num_playouts: 100,
+            time_limit: None,
+            num_threads: 1,
+            debug: false,
+            exploration_constant: 2.0,
+            evaluator: Arc::new(evaluator::SolvedTasks),
+            scoring: Scoring::Tokens,
+            strategy: Strategy::Mcts,
+            transpositions: Transpositions::default(),
+            seed: Some(3),
+        })
+        .unwrap();
+        assert!(state.placed_tiles().count() > 0);
+        assert_eq!(
+            tiles.len(),
+            state.placed_tiles().count() + state.remaining_tiles().count()
+        );
+    }
+
+    #[test]
+    fn parse_strategy() {
+        for strategy in [Strategy::Mcts, Strategy::Exact] {
+            assert_eq!(Ok(strategy), strategy.to_string().parse());
+        }
+        assert!("minimax".parse::<Strategy>().is_err());
+    }
+
+    #[test]
+    fn transposition_tables() {
+        let table = |transpositions| {
+            let param = SolverParameters {
+                tiles: parse_file("../resources/tiles.json").unwrap(),
+                budget: None,
+                output_file: None,
+                output_dir: None,
+                print_statistics: false,
+                print_moves: false,
+                num_playouts: 5000,
+                time_limit: None,
+                num_threads: 1,
+                debug: false,
+                exploration_constant: 2.0,
+                evaluator: Arc::new(evaluator::SolvedTasks),
+                scoring: Scoring::Tokens,
+                strategy: Strategy::Mcts,
+                transpositions,
+                seed: Some(11),
+            };
+            let mut search = AnytimeSearch::new(&param);
+            search.run_playouts(param.num_playouts);
+            (search.best_moves(), search.table_statistics())
+        };
+
+        let (_, off) = table(Transpositions::Off);
+        assert_eq!(TableStatistics::default(), off);
+
+        let (exact_moves, exact) = table(Transpositions::Exact);
+        assert!(exact.hits > 0 && exact.hits <= exact.lookups, "{:?}", exact);
+        assert_eq!(0, exact.collisions);
+        assert_eq!(exact.hits as f64 / exact.lookups as f64, exact.hit_rate);
+
+        // Without collisions, a table large enough for all nodes finds the same nodes.
+        let (approx_moves, approx) = table(Transpositions::Approx(None));
+        assert_eq!(exact, approx);
+        assert_eq!(exact_moves, approx_moves);
+
+        let (moves, small) = table(Transpositions::Approx(Some(16)));
+        assert!(small.hits < exact.hits, "{:?}", small);
+        assert_eq!(exact_moves.len(), moves.len());
+    }
+
+    #[test]
+    fn parse_transpositions() {
+        for transpositions in [
+            Transpositions::Off,
+            Transpositions::Approx(None),
+            Transpositions::Approx(Some(4096)),
+            Transpositions::Exact,
+        ] {
+            assert_eq!(Ok(transpositions), transpositions.to_string().parse());
+        }
+        assert!("approx:".parse::<Transpositions>().is_err());
+        assert!("exact:10".parse::<Transpositions>().is_err());
+        assert!("lru".parse::<Transpositions>().is_err());
+    }
 }
@@ -0,0 +1,172 @@
+//! Market wheel the tiles of a [`Game`](crate::game::Game) are bought from.
+//!
+//! The moon meeple stands on one of the slots of the wheel. Only the next tiles clockwise from
+//! the meeple can be bought and the meeple moves onto the slot of the bought tile. Once only a
+//! few tiles are left, the empty slots are refilled from the draw pile.
+
+use crate::nova_luna::TileId;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// Number of slots of the wheel, including the slot of the moon meeple.
+pub const MARKET_SLOTS: usize = 12;
+/// Number of tiles clockwise from the meeple which can be bought.
+pub const WINDOW: usize = 3;
+/// The market is refilled once no more than this number of tiles are left.
+pub const REFILL_THRESHOLD: usize = 2;
+
+/// Tile which can be bought from a slot of the market.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Purchase {
+    pub slot: usize,
+    pub tile: TileId,
+}
+
+#[derive(Clone, Debug)]
+pub struct Market {
+    slots: [Option<TileId>; MARKET_SLOTS],
+    meeple: usize,
+    /// Remaining tiles, the next tile to draw is the last one.
+    draw_pile: Vec<TileId>,
+}
+
+impl Market {
+    /// Shuffles the tiles into the draw pile and fills the wheel with the meeple on the first
+    /// slot.
+    pub fn new<R: Rng>(tiles: Vec<TileId>, rng: &mut R) -> Market {
+        let mut draw_pile = tiles;
+        draw_pile.shuffle(rng);
+        Market::with_draw_pile(draw_pile)
+    }
+
+    /// Fills the wheel from the draw pile without shuffling it, drawing from the end of the
+    /// pile.
+    pub fn with_draw_pile(draw_pile: Vec<TileId>) -> Market {
+        let mut market = Market {
+            slots: [None; MARKET_SLOTS],
+            meeple: 0,
+            draw_pile,
+        };
+        market.refill();
+        market
+    }
+
+    pub fn meeple(&self) -> usize {
+        self.meeple
+    }
+
+    /// Returns the tile on the slot.
+    pub fn slot(&self, slot: usize) -> Option<TileId> {
+        self.slots[slot % MARKET_SLOTS]
+    }
+
+    /// Returns the tiles on the wheel, clockwise from the meeple.
+    pub fn tiles(&self) -> impl Iterator<Item = Purchase> + '_ {
+        (1..MARKET_SLOTS)
+            .map(move |offset| (self.meeple + offset) % MARKET_SLOTS)
+            .filter_map(move |slot| self.slots[slot].map(|tile| Purchase { slot, tile }))
+    }
+
+    /// Returns the tiles which can be bought, the nearest to the meeple first.
+    pub fn purchases(&self) -> Vec<Purchase> {
+        self.tiles().take(WINDOW).collect()
+    }
+
+    pub fn draw_pile(&self) -> &[TileId] {
+        &self.draw_pile
+    }
+
+    /// Replaces the tiles which have not been drawn yet, e.g. by a sampled order of the tiles
+    /// still in the box.
+    pub fn set_draw_pile(&mut self, draw_pile: Vec<TileId>) {
+        self.draw_pile = draw_pile;
+    }
+
+    /// Returns whether neither the wheel nor the draw pile has tiles left.
+    pub fn is_empty(&self) -> bool {
+        self.draw_pile.is_empty() && self.tiles().next().is_none()
+    }
+
+    /// Buys the tile on the slot, if it is one of the purchases, moves the meeple onto the slot
+    /// and refills the wheel when only a few tiles are left.
+    pub fn buy(&mut self, slot: usize) -> Option<TileId> {
+        if !self.purchases().iter().any(|p| p.slot == slot) {
+            return None;
+        }
+        let tile = self.slots[slot].take();
+        self.meeple = slot;
+        if self.tiles().count() <= REFILL_THRESHOLD {
+            self.refill();
+        }
+        tile
+    }
+
+    /// Buys the nearest purchasable tile with the ID.
+    pub fn buy_tile(&mut self, tile: TileId) -> Option<TileId> {
+        let purchase = self.purchases().into_iter().find(|p| p.tile == tile)?;
+        self.buy(purchase.slot)
+    }
+
+    /// Fills the empty slots clockwise from the meeple.
+    fn refill(&mut self) {
+        for offset in 1..MARKET_SLOTS {
+            let slot = (self.meeple + offset) % MARKET_SLOTS;
+            if self.slots[slot].is_none() {
+                self.slots[slot] = self.draw_pile.pop();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nova_luna::{Color, State, Tile};
+
+    fn tile_ids(n: usize) -> Vec<TileId> {
+        let tiles = (1..=n)
+            .map(|cost| Tile::new(cost, Color::Red, vec![]))
+            .collect();
+        State::with_tiles(tiles).tile_ids().collect()
+    }
+
+    fn purchased_slots(market: &Market) -> Vec<usize> {
+        market.purchases().iter().map(|p| p.slot).collect()
+    }
+
+    #[test]
+    fn purchases() {
+        let ids = tile_ids(14);
+        let mut market = Market::with_draw_pile(ids.clone());
+        assert_eq!(None, market.slot(0));
+        assert_eq!(Some(ids[13]), market.slot(1));
+        assert_eq!(Some(ids[3]), market.slot(11));
+        assert_eq!(3, market.draw_pile().len());
+        assert_eq!(vec![1, 2, 3], purchased_slots(&market));
+
+        assert_eq!(None, market.buy(4));
+        assert_eq!(Some(ids[11]), market.buy(3));
+        assert_eq!(3, market.meeple());
+        assert_eq!(vec![4, 5, 6], purchased_slots(&market));
+    }
+
+    #[test]
+    fn window_skips_empty_slots_and_refills() {
+        let ids = tile_ids(14);
+        let mut market = Market::with_draw_pile(ids);
+        for slot in 1..=8 {
+            assert!(market.buy(slot).is_some());
+        }
+        // The tiles on slots 9 to 11 remain, after buying one the wheel is refilled.
+        assert_eq!(vec![9, 10, 11], purchased_slots(&market));
+        assert!(market.buy(11).is_some());
+        assert_eq!(vec![0, 1, 2], purchased_slots(&market));
+        assert_eq!(5, market.tiles().count());
+        assert!(market.draw_pile().is_empty());
+
+        while let Some(p) = market.purchases().first().copied() {
+            market.buy(p.slot);
+        }
+        assert!(market.is_empty());
+    }
+}
@@ -0,0 +1,341 @@
+//! Search of the next move in a [`Game`] of several players, anticipating the moves of the
+//! opponents.
+//!
+//! Every player is rated by the tokens they placed relative to the best of their opponents, so
+//! the search also values buying tiles an opponent needs to solve their tasks.
+//!
+//! The order of the draw pile of the market is unknown during a game. [`determinized_search`]
+//! searches several sampled orders and combines the statistics of the moves.
+
+use crate::error::SolverError;
+use crate::game::{Game, Player};
+use crate::nova_luna::{Move, Tile};
+use crate::scoring::{reward, SCORE_SCALE};
+use crate::solver::{SeededUCTPolicy, ThreadRng};
+use mcts::{CycleBehaviour, Evaluator, MCTSManager, SearchHandle, MCTS};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::fmt;
+use std::str::FromStr;
+
+/// Value of an unsolved task missing no color, relative to a placed token.
+const PARTIAL_PROGRESS_WEIGHT: f64 = 0.5;
+
+/// Model of the moves of the opponents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Opponents {
+    /// Every player maximizes their own advantage (max-n).
+    MaxN,
+    /// All opponents minimize the advantage of the player searching for a move.
+    Paranoid,
+}
+
+impl FromStr for Opponents {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Opponents, String> {
+        match s {
+            "max-n" => Ok(Opponents::MaxN),
+            "paranoid" => Ok(Opponents::Paranoid),
+            _ => Err(format!("unknown opponent model \"{}\"", s)),
+        }
+    }
+}
+
+impl fmt::Display for Opponents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Opponents::MaxN => write!(f, "max-n"),
+            Opponents::Paranoid => write!(f, "paranoid"),
+        }
+    }
+}
+
+/// Rates the progress of the player: the placed tokens, the progress of the unsolved tasks and
+/// all tokens of the game as bonus for winning.
+pub fn progress(game: &Game, player: Player) -> f64 {
+    let board = game.player(player).board();
+    let placed = (game.tokens() - game.tokens_left(player)) as f64;
+    let bonus = if game.winners().contains(&player) {
+        game.tokens() as f64
+    } else {
+        0.0
+    };
+    placed + PARTIAL_PROGRESS_WEIGHT * board.task_progress() + bonus
+}
+
+/// Rates the players of the game by their progress relative to their best opponent.
+pub struct GameEvaluator {
+    opponents: Opponents,
+    /// Player searching for a move, whom the opponents play against in the paranoid model.
+    root: Player,
+}
+
+impl Evaluator<GameMCTS> for GameEvaluator {
+    type StateEvaluation = Vec<i64>;
+
+    fn evaluate_new_state(
+        &self,
+        game: &Game,
+        moves: &Vec<Move>,
+        _: Option<SearchHandle<GameMCTS>>,
+    ) -> (Vec<()>, Vec<i64>) {
+        let progress: Vec<f64> = game.players().map(|p| progress(game, p)).collect();
+        let advantages = progress
+            .iter()
+            .enumerate()
+            .map(|(p, own)| {
+                let best_opponent = progress
+                    .iter()
+                    .enumerate()
+                    .filter(|(o, _)| *o != p)
+                    .map(|(_, v)| *v)
+                    .fold(f64::NEG_INFINITY, f64::max);
+                reward(own - best_opponent)
+            })
+            .collect();
+        (vec![(); moves.len()], advantages)
+    }
+
+    fn evaluate_existing_state(
+        &self,
+        _: &Game,
+        evaln: &Vec<i64>,
+        _: SearchHandle<GameMCTS>,
+    ) -> Vec<i64> {
+        evaln.clone()
+    }
+
+    fn interpret_evaluation_for_player(&self, evaln: &Vec<i64>, player: &Player) -> i64 {
+        match self.opponents {
+            Opponents::MaxN => evaln[player.index()],
+            Opponents::Paranoid if *player == self.root => evaln[self.root.index()],
+            Opponents::Paranoid => -evaln[self.root.index()],
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GameMCTS;
+
+impl MCTS for GameMCTS {
+    type State = Game;
+    type Eval = GameEvaluator;
+    type TreePolicy = SeededUCTPolicy;
+    type NodeData = ();
+    type TranspositionTable = ();
+    type ExtraThreadData = ThreadRng;
+
+    fn cycle_behaviour(&self) -> CycleBehaviour<Self> {
+        CycleBehaviour::UseCurrentEvalWhenCycleDetected
+    }
+}
+
+/// Search of the next move of a game.
+pub struct GameSearch {
+    mcts: MCTSManager<GameMCTS>,
+}
+
+impl GameSearch {
+    pub fn new(
+        game: Game,
+        opponents: Opponents,
+        exploration_constant: f64,
+        seed: u64,
+    ) -> GameSearch {
+        let root = game.next_player();
+        let mcts = MCTSManager::new(
+            game,
+            GameMCTS,
+            GameEvaluator { opponents, root },
+            SeededUCTPolicy::new(exploration_constant, seed),
+            (),
+        );
+        GameSearch { mcts }
+    }
+
+    pub fn run_playouts(&mut self, num_playouts: u32, num_threads: usize) {
+        self.mcts
+            .playout_n_parallel(num_playouts, num_threads.max(1));
+    }
+
+    /// Returns the most visited move of the next player.
+    pub fn best_move(&self) -> Option<Move> {
+        self.mcts.best_move()
+    }
+
+    /// Returns the statistics of the moves of the next player, the most visited first.
+    pub fn move_statistics(&self) -> Vec<MoveStatistics> {
+        let mut moves: Vec<MoveStatistics> = self
+            .mcts
+            .tree()
+            .root_node()
+            .moves()
+            .map(|m| MoveStatistics {
+                mov: *m.get_move(),
+                visits: m.visits(),
+                sum_rewards: m.sum_rewards(),
+            })
+            .collect();
+        moves.sort_by_key(|m| std::cmp::Reverse(m.visits));
+        moves
+    }
+}
+
+/// How often the search tried a move and how well the move did.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MoveStatistics {
+    pub mov: Move,
+    pub visits: u64,
+    sum_rewards: i64,
+}
+
+impl MoveStatistics {
+    /// Returns the mean advantage of the player over their best opponent after the move.
+    pub fn mean_value(&self) -> f64 {
+        if self.visits == 0 {
+            0.0
+        } else {
+            self.sum_rewards as f64 / self.visits as f64 / SCORE_SCALE
+        }
+    }
+}
+
+pub struct GameSearchParameters {
+    pub opponents: Opponents,
+    /// Number of sampled orders of the draw pile.
+    pub samples: usize,
+    /// Number of playouts per sample.
+    pub num_playouts: u32,
+    pub num_threads: usize,
+    pub exploration_constant: f64,
+    pub seed: u64,
+}
+
+/// Searches the game with the draw pile shuffled into several orders and sums up the statistics
+/// of the moves of all samples, the most visited move first.
+///
+/// The draw pile holds the tiles still in the box, which have to be tiles of the game which
+/// have neither been bought nor are in the market.
+///
+/// # Panics
+///
+/// Panics if the game has no market.
+pub fn determinized_search(
+    game: &Game,
+    draw_pile: &[Tile],
+    param: &GameSearchParameters,
+) -> Result<Vec<MoveStatistics>, SolverError> {
+    game.clone().set_draw_pile(draw_pile)?;
+
+    let mut moves: Vec<MoveStatistics> = vec![];
+    for sample in 0..param.samples {
+        let seed = param.seed.wrapping_add(sample as u64);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut pile = draw_pile.to_vec();
+        pile.shuffle(&mut rng);
+        let mut game = game.clone();
+        game.set_draw_pile(&pile)?;
+
+        let mut search = GameSearch::new(game, param.opponents, param.exploration_constant, seed);
+        search.run_playouts(param.num_playouts, param.num_threads);
+        for stats in search.move_statistics() {
+            match moves.iter_mut().find(|m| m.mov == stats.mov) {
+                Some(m) => {
+                    m.visits += stats.visits;
+                    m.sum_rewards += stats.sum_rewards;
+                }
+                None => moves.push(stats),
+            }
+        }
+    }
+    moves.sort_by_key(|m| std::cmp::Reverse(m.visits));
+    Ok(moves)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nova_luna::{Color, Position, Task};
+    use mcts::GameState;
+
+    fn buy(game: &mut Game, tile: &Tile, pos: Position) {
+        let board = game.player(game.next_player()).board();
+        game.make_move(&Move::new(board.tile_id(tile).unwrap(), pos));
+    }
+
+    #[test]
+    fn denies_tiles_to_opponents() {
+        let start = Tile::new(1, Color::Yellow, vec![]);
+        let red = Tile::new(2, Color::Red, vec![Task::new(vec![Color::Teal])]);
+        let teal = Tile::new(1, Color::Teal, vec![]);
+        let blue = Tile::new(3, Color::Blue, vec![]);
+        let tiles = vec![start.clone(), red.clone(), teal.clone(), blue];
+
+        for opponents in [Opponents::MaxN, Opponents::Paranoid] {
+            let mut game = Game::new(tiles.clone(), 2);
+            buy(&mut game, &start, Position::new(0, 0));
+            buy(&mut game, &red, Position::new(0, 0));
+            // Player 1 needs the teal tile and moves next, unless player 0 buys the cheap teal
+            // tile instead of the blue one.
+            assert_eq!(Player::new(0), game.next_player());
+
+            let mut search = GameSearch::new(game.clone(), opponents, 2.0, 42);
+            search.run_playouts(1000, 1);
+            let best = search.best_move().unwrap();
+            let id = game.player(Player::new(0)).board().tile_id(&teal);
+            assert_eq!(id, Some(best.tile()), "{}", opponents);
+        }
+    }
+
+    #[test]
+    fn determinized_search_over_draw_orders() {
+        let mut tiles = crate::parse_file("../resources/tiles.json").unwrap();
+        tiles.extend(tiles.clone());
+        let mut rng = StdRng::seed_from_u64(42);
+        let game = Game::with_market(tiles.clone(), 2, &mut rng);
+        let draw_pile: Vec<Tile> = game
+            .market()
+            .unwrap()
+            .draw_pile()
+            .iter()
+            .map(|id| game.player(Player::new(0)).board().tile(*id).clone())
+            .collect();
+        assert_eq!(11, draw_pile.len());
+        let param = GameSearchParameters {
+            opponents: Opponents::MaxN,
+            samples: 3,
+            num_playouts: 200,
+            num_threads: 1,
+            exploration_constant: 2.0,
+            seed: 7,
+        };
+
+        let moves = determinized_search(&game, &draw_pile, &param).unwrap();
+        assert_eq!(600, moves.iter().map(|m| m.visits).sum::<u64>());
+        assert!(moves.windows(2).all(|w| w[0].visits >= w[1].visits));
+        let available = game.available_moves();
+        assert!(moves.iter().all(|m| available.contains(&m.mov)));
+        assert_eq!(
+            moves,
+            determinized_search(&game, &draw_pile, &param).unwrap()
+        );
+
+        // The tiles in the market cannot be in the box as well.
+        let mut too_many = draw_pile.clone();
+        too_many.extend(tiles);
+        assert!(matches!(
+            determinized_search(&game, &too_many, &param),
+            Err(SolverError::UnavailableTile { .. })
+        ));
+    }
+
+    #[test]
+    fn parse() {
+        for opponents in [Opponents::MaxN, Opponents::Paranoid] {
+            assert_eq!(Ok(opponents), opponents.to_string().parse());
+        }
+        assert!("minimax".parse::<Opponents>().is_err());
+    }
+}
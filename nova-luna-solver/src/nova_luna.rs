@@ -1,17 +1,55 @@
+use crate::board::Board;
+use crate::components::Components;
+use crate::error::SolverError;
+use crate::zobrist;
 use mcts::transposition_table::TranspositionHash;
 use mcts::GameState;
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
 use serde::ser::SerializeSeq;
-use serde::{Deserialize, Serialize, Serializer};
-use std::collections::hash_map::DefaultHasher;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::{HashMap, HashSet};
-use std::hash::{Hash, Hasher};
-use std::iter;
-
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize)]
-pub struct Position(isize, isize);
+use std::fmt;
+use std::hash::Hash;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Maximum number of tiles a state can hold.
+pub const MAX_TILES: usize = 128;
+/// Maximum number of tasks a single tile can have.
+pub const MAX_TASKS: usize = 8;
+/// Number of plies in which [`State::available_moves`] leaves out moves leading to symmetric
+/// copies of the boards of other moves. Later boards are rarely symmetric, so looking for copies
+/// costs more than it saves.
+const SYMMETRY_PLIES: usize = 3;
+
+/// Placed tiles of a board, ordered by their position, which identify the board regardless of
+/// the order the tiles were placed in and of its position and orientation.
+pub(crate) type BoardKey = Vec<(isize, isize, TileId)>;
+
+type Symmetry = fn(isize, isize) -> (isize, isize);
+
+/// Rotations and reflections of the grid, which leave the adjacency of positions unchanged.
+const SYMMETRIES: [Symmetry; 8] = [
+    |x, y| (x, y),
+    |x, y| (-y, x),
+    |x, y| (-x, -y),
+    |x, y| (y, -x),
+    |x, y| (-x, y),
+    |x, y| (y, x),
+    |x, y| (x, -y),
+    |x, y| (-y, -x),
+];
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position(pub(crate) isize, pub(crate) isize);
 
 impl Position {
-    fn adjacent(&self) -> [Position; 4] {
+    pub fn new(x: isize, y: isize) -> Position {
+        Position(x, y)
+    }
+
+    pub(crate) fn adjacent(&self) -> [Position; 4] {
         let mut result = [Position(0, 0); 4];
         result[0] = self.offset(1, 0);
         result[1] = self.offset(0, 1);
@@ -34,8 +72,7 @@ pub enum Color {
     Red = 3,
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(from = "VecColors")]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize)]
 pub struct Task {
     colors: Vec<Color>,
     solved: bool,
@@ -52,6 +89,46 @@ impl From<VecColors> for Task {
     }
 }
 
+/// Task as serialized with its solved flag.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SolvedTask {
+    colors: Vec<Color>,
+    #[serde(default)]
+    solved: bool,
+}
+
+impl<'de> Deserialize<'de> for Task {
+    /// Reads a task either as list of colors or as object with the colors and the solved flag.
+    fn deserialize<D>(deserializer: D) -> Result<Task, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TaskVisitor;
+
+        impl<'de> Visitor<'de> for TaskVisitor {
+            type Value = Task;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a list of colors or a task")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Task, A::Error> {
+                Vec::deserialize(de::value::SeqAccessDeserializer::new(seq)).map(Task::new)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Task, A::Error> {
+                let task = SolvedTask::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                let mut result = Task::new(task.colors);
+                result.solved = task.solved;
+                Ok(result)
+            }
+        }
+
+        deserializer.deserialize_any(TaskVisitor)
+    }
+}
+
 impl Task {
     pub fn new(colors: Vec<Color>) -> Task {
         let has_colors = [Color::Blue, Color::Yellow, Color::Teal, Color::Red]
@@ -91,11 +168,19 @@ impl Task {
         adjacent_tiles
     }
 
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
     fn has_color(&self, color: Color) -> bool {
-        self.has_colors[(color as usize)]
+        self.has_colors[color as usize]
     }
 
-    fn is_solved(&self, pos: Position, state: &HashMap<Position, Tile>) -> bool {
+    /// Checks the task by searching the color groups adjacent to the position on the board.
+    ///
+    /// This is the reference for the incremental check in [`State::make_move`], which uses the
+    /// color components maintained by the state instead.
+    pub(crate) fn is_solved(&self, pos: Position, state: &HashMap<Position, Tile>) -> bool {
         let mut map = HashMap::new();
         for p in pos.adjacent() {
             if let Some(tile) = state.get(&p) {
@@ -121,6 +206,36 @@ impl Task {
         }
         found_colors.is_empty()
     }
+
+    fn is_solved_by(&self, counts: &[usize; 4]) -> bool {
+        let mut required = [0; 4];
+        for color in &self.colors {
+            required[*color as usize] += 1;
+        }
+        required.iter().zip(counts).all(|(r, c)| r <= c)
+    }
+
+    /// Returns whether the task only lacks colors of the given colors, bit `c` for color `c`.
+    fn lacks_only(&self, counts: &[usize; 4], colors: u8) -> bool {
+        let mut required = [0; 4];
+        for color in &self.colors {
+            required[*color as usize] += 1;
+        }
+        (0..4).all(|c| required[c] <= counts[c] || colors & (1 << c) != 0)
+    }
+
+    /// Returns the fraction of the required colors which are already adjacent.
+    fn progress_by(&self, counts: &[usize; 4]) -> f64 {
+        let mut remaining = *counts;
+        let mut found = 0;
+        for color in &self.colors {
+            if remaining[*color as usize] > 0 {
+                remaining[*color as usize] -= 1;
+                found += 1;
+            }
+        }
+        found as f64 / self.colors.len() as f64
+    }
 }
 
 #[derive(Clone, Hash, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -131,28 +246,215 @@ pub struct Tile {
 }
 
 impl Tile {
+    /// Range of the moon track costs printed on the tiles.
+    pub const COST_RANGE: RangeInclusive<usize> = 1..=7;
+    /// Range of the number of tasks printed on the tiles.
+    pub const TASKS_RANGE: RangeInclusive<usize> = 1..=3;
+    /// Range of the number of colors required by a single task.
+    pub const TASK_COLORS_RANGE: RangeInclusive<usize> = 1..=4;
+
     #[cfg(test)]
     pub fn new(cost: usize, color: Color, tasks: Vec<Task>) -> Tile {
         Tile { color, cost, tasks }
     }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn cost(&self) -> usize {
+        self.cost
+    }
+
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+
+    /// Returns the solved flags of the tasks, bit `i` for task `i`.
+    pub(crate) fn solved_flags(&self) -> u8 {
+        self.tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| task.solved)
+            .fold(0, |solved, (idx, _)| solved | (1 << idx))
+    }
+
+    /// Returns the tile with the solved flags of the tasks set to the flags.
+    pub(crate) fn with_solved_flags(&self, solved: u8) -> Tile {
+        let mut tile = self.clone();
+        for (idx, task) in tile.tasks.iter_mut().enumerate() {
+            task.solved = solved & (1 << idx) != 0;
+        }
+        tile
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+/// Tiles on a board with their positions.
+pub type BoardTiles = Vec<(Position, Tile)>;
+
+/// Index of a tile in the tiles of a [`State`], identical tiles share the same index.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct TileId(u8);
+
+impl TileId {
+    pub(crate) fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
 pub struct Move {
-    tile: Tile,
+    tile: TileId,
     position: Position,
 }
 
 impl Move {
-    pub fn new(tile: Tile, position: Position) -> Move {
+    pub fn new(tile: TileId, position: Position) -> Move {
         Move { tile, position }
     }
+
+    /// Returns the move with its position relative to the origin.
+    pub(crate) fn relative_to(&self, origin: Position) -> Move {
+        Move::new(
+            self.tile,
+            Position(self.position.0 - origin.0, self.position.1 - origin.1),
+        )
+    }
+
+    /// Shifts the position of the move by the offset, which undoes [`Move::relative_to`] with
+    /// the offset as origin.
+    pub(crate) fn shifted_by(&self, offset: Position) -> Move {
+        Move::new(self.tile, self.position.offset(offset.0, offset.1))
+    }
+
+    pub fn tile(&self) -> TileId {
+        self.tile
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+}
+
+/// A tile on the board, serialized like [`Tile`] with the solved flags of the board.
+#[derive(Serialize)]
+pub struct PlacedTile<'a> {
+    position: Position,
+    tile: TileView<'a>,
+}
+
+#[derive(Serialize)]
+struct TileView<'a> {
+    color: Color,
+    cost: usize,
+    tasks: Vec<TaskView<'a>>,
+}
+
+#[derive(Serialize)]
+struct TaskView<'a> {
+    colors: &'a [Color],
+    solved: bool,
+}
+
+impl<'a> PlacedTile<'a> {
+    fn new(position: Position, tile: &'a Tile, solved: u8) -> PlacedTile<'a> {
+        let tasks = tile
+            .tasks
+            .iter()
+            .enumerate()
+            .map(|(idx, task)| TaskView {
+                colors: &task.colors,
+                solved: solved & (1 << idx) != 0,
+            })
+            .collect();
+        PlacedTile {
+            position,
+            tile: TileView {
+                color: tile.color,
+                cost: tile.cost,
+                tasks,
+            },
+        }
+    }
+}
+
+/// Limit of the tiles taken from the tiles of a [`State`], which are then a pool to choose from
+/// instead of a hand which is placed completely.
+///
+/// The budget is always spent: tiles are taken until no further tile of the pool fits into it,
+/// even if fewer tiles would score more with
+/// [`Scoring::TokensPerCost`](crate::Scoring::TokensPerCost).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TileBudget {
+    /// Number of tiles which can be taken.
+    Count(usize),
+    /// Sum of the moon track costs of the tiles which can be taken.
+    Cost(usize),
+}
+
+impl FromStr for TileBudget {
+    type Err = String;
+
+    /// Parses "count:<tiles>" or "cost:<steps>".
+    fn from_str(s: &str) -> Result<TileBudget, String> {
+        let (kind, limit) = s
+            .split_once(':')
+            .ok_or_else(|| format!("unknown budget \"{}\"", s))?;
+        let limit = limit
+            .parse()
+            .map_err(|_| format!("invalid limit in \"{}\"", s))?;
+        match kind {
+            "count" => Ok(TileBudget::Count(limit)),
+            "cost" => Ok(TileBudget::Cost(limit)),
+            _ => Err(format!("unknown budget \"{}\"", s)),
+        }
+    }
 }
 
+impl fmt::Display for TileBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TileBudget::Count(n) => write!(f, "count:{}", n),
+            TileBudget::Cost(n) => write!(f, "cost:{}", n),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Placement {
+    cell: u32,
+    tile: TileId,
+    solved: u8,
+    /// Representatives of the color components the placement attached, in order, so that
+    /// [`State::unmake_move`] can split them again.
+    merged: [u8; 4],
+    num_merged: u8,
+    /// [Corner](State::corner) of the board before the placement, restored by
+    /// [`State::unmake_move`].
+    corner: (u16, u16),
+}
+
+/// Board and remaining tiles of a game.
+///
+/// The distinct tiles are kept in a shared list and referenced by their [`TileId`], while the
+/// placements are stored in fixed-size arrays, so cloning a state only allocates the grid of the
+/// board.
 #[derive(Clone, Debug)]
 pub struct State {
-    board: HashMap<Position, Tile>,
-    tiles: Vec<Tile>,
+    tiles: Arc<[Tile]>,
+    available: [u8; MAX_TILES],
+    board: Board,
+    placements: [Placement; MAX_TILES],
+    num_placements: usize,
+    components: Components,
+    budget: Option<TileBudget>,
+    /// [Keys](zobrist::tile_key) of the distinct tiles.
+    tile_keys: Arc<[u64]>,
+    /// Sum of the [keys](zobrist::key) of the placed tiles.
+    keys: u64,
+    /// Column and row of the lowest corner of the placed tiles in the grid.
+    corner: (u16, u16),
 }
 
 impl State {
@@ -161,19 +463,522 @@ impl State {
         State::with_tiles(vec![])
     }
 
+    /// Creates an empty board with the given tiles to place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than [`MAX_TILES`] tiles or a tile has more than [`MAX_TASKS`]
+    /// tasks.
     pub fn with_tiles(tiles: Vec<Tile>) -> State {
+        assert!(tiles.len() <= MAX_TILES, "too many tiles");
+        assert!(
+            tiles.iter().all(|t| t.tasks.len() <= MAX_TASKS),
+            "too many tasks"
+        );
+        let board = Board::new(tiles.len());
+        let mut distinct: Vec<Tile> = vec![];
+        let mut available = [0; MAX_TILES];
+        for tile in tiles {
+            match distinct.iter().position(|t| *t == tile) {
+                Some(idx) => available[idx] += 1,
+                None => {
+                    available[distinct.len()] = 1;
+                    distinct.push(tile);
+                }
+            }
+        }
         State {
-            board: HashMap::new(),
-            tiles,
+            tile_keys: distinct.iter().map(zobrist::tile_key).collect(),
+            tiles: distinct.into(),
+            available,
+            board,
+            placements: [Placement::default(); MAX_TILES],
+            num_placements: 0,
+            components: Components::default(),
+            budget: None,
+            keys: 0,
+            corner: (0, 0),
+        }
+    }
+
+    /// Creates an empty board with a pool of tiles, of which only the tiles within the budget
+    /// can be placed. The game ends once no further tile of the pool fits into the budget.
+    ///
+    /// # Panics
+    ///
+    /// Panics like [`State::with_tiles`].
+    pub fn with_budget(tiles: Vec<Tile>, budget: TileBudget) -> State {
+        State {
+            budget: Some(budget),
+            ..State::with_tiles(tiles)
+        }
+    }
+
+    pub fn budget(&self) -> Option<TileBudget> {
+        self.budget
+    }
+
+    /// Limits the tiles of the game to the budget, which includes the tiles already placed.
+    pub fn set_budget(&mut self, budget: Option<TileBudget>) {
+        self.budget = budget;
+    }
+
+    /// Limits the tiles placed from now on to the budget, e.g. to the steps a player can still
+    /// advance on the moon track.
+    pub fn set_remaining_budget(&mut self, remaining: TileBudget) {
+        self.budget = Some(match remaining {
+            TileBudget::Count(n) => TileBudget::Count(self.num_placements + n),
+            TileBudget::Cost(cost) => TileBudget::Cost(self.spent_cost() + cost),
+        });
+    }
+
+    /// Returns the highest cost the next tile may have within the budget, or `None` if the
+    /// budget is used up.
+    pub fn max_cost(&self) -> Option<usize> {
+        match self.budget {
+            None => Some(usize::MAX),
+            Some(TileBudget::Count(n)) => (self.num_placements < n).then_some(usize::MAX),
+            Some(TileBudget::Cost(cost)) => cost.checked_sub(self.spent_cost()),
         }
     }
 
+    /// Returns the IDs of the tiles which are still available and fit into the budget.
+    pub fn affordable_tiles(&self) -> impl Iterator<Item = TileId> + '_ {
+        let max_cost = self.max_cost().unwrap_or(0);
+        self.tile_ids()
+            .filter(move |id| self.available(*id) > 0 && self.tile(*id).cost <= max_cost)
+    }
+
+    pub fn tile(&self, id: TileId) -> &Tile {
+        &self.tiles[id.0 as usize]
+    }
+
+    /// Returns the ID of the tile, if the tile is part of this game.
+    pub fn tile_id(&self, tile: &Tile) -> Option<TileId> {
+        self.tiles
+            .iter()
+            .position(|t| t == tile)
+            .map(|idx| TileId(idx as u8))
+    }
+
+    /// Returns the IDs of the distinct tiles of the game.
+    pub fn tile_ids(&self) -> impl Iterator<Item = TileId> {
+        (0..self.tiles.len()).map(|idx| TileId(idx as u8))
+    }
+
+    /// Returns how many tiles of the ID have not been placed yet.
+    pub fn available(&self, id: TileId) -> usize {
+        self.available[id.0 as usize] as usize
+    }
+
+    /// Returns the free positions a tile can be placed on, ordered by their cell in the grid.
+    pub fn free_positions(&self) -> Vec<Position> {
+        let mut empty_positions: Vec<Position> = if self.num_placements == 0 {
+            vec![Position(0, 0)]
+        } else {
+            self.placements()
+                .iter()
+                .flat_map(|p| self.board.position(p.cell as usize).adjacent())
+                .filter(|pos| self.board.cell(*pos).is_some() && self.board.get(*pos).is_none())
+                .collect()
+        };
+        empty_positions.sort_unstable_by_key(|pos| self.board.cell(*pos));
+        empty_positions.dedup();
+        empty_positions
+    }
+
+    /// Returns the tiles which have not been placed yet.
+    pub fn remaining_tiles(&self) -> impl Iterator<Item = &Tile> {
+        self.tiles
+            .iter()
+            .zip(self.available.iter())
+            .flat_map(|(tile, n)| std::iter::repeat_n(tile, *n as usize))
+    }
+
+    /// Returns the placed tiles in the order they were placed.
+    pub fn placed_tiles(&self) -> impl Iterator<Item = PlacedTile<'_>> {
+        self.placements().iter().map(move |p| {
+            PlacedTile::new(
+                self.board.position(p.cell as usize),
+                self.tile(p.tile),
+                p.solved,
+            )
+        })
+    }
+
+    /// Returns the placed tiles by their position, with the solved flags stored in the tasks.
+    #[cfg(test)]
+    fn tiles_by_position(&self) -> HashMap<Position, Tile> {
+        self.board_tiles().into_iter().collect()
+    }
+
+    /// Returns the placed tiles with their positions in the order they were placed, with the
+    /// solved flags stored in the tasks.
+    pub fn board_tiles(&self) -> BoardTiles {
+        self.placements()
+            .iter()
+            .map(|p| {
+                let tile = self.tile(p.tile).with_solved_flags(p.solved);
+                (self.board.position(p.cell as usize), tile)
+            })
+            .collect()
+    }
+
+    /// Returns the IDs of the placed tiles with the solved flags of their tasks.
+    pub(crate) fn placed_ids(&self) -> impl Iterator<Item = (TileId, u8)> + '_ {
+        self.placements().iter().map(|p| (p.tile, p.solved))
+    }
+
+    /// Returns the IDs of the placed tiles with the tasks which are solved or only lack colors of
+    /// the tiles which still can be placed. Other tasks cannot be solved anymore, because only
+    /// placing a tile of a color adds to the adjacent tiles of that color.
+    pub(crate) fn solvable_placed(&self) -> impl Iterator<Item = (TileId, u8)> + '_ {
+        let remaining_colors = self
+            .affordable_tiles()
+            .fold(0u8, |colors, id| colors | (1 << self.tile(id).color as u8));
+        (0..self.num_placements).map(move |idx| {
+            let placement = self.placements[idx];
+            let tasks = &self.tile(placement.tile).tasks;
+            if placement.solved.count_ones() as usize == tasks.len() {
+                return (placement.tile, placement.solved);
+            }
+            let counts = self.count_adjacent_colors(idx);
+            let solvable = tasks
+                .iter()
+                .enumerate()
+                .filter(|(_, task)| task.lacks_only(&counts, remaining_colors))
+                .fold(placement.solved, |solvable, (t, _)| solvable | (1 << t));
+            (placement.tile, solvable)
+        })
+    }
+
+    /// Describes the tile which is placed by the move.
+    pub fn placed_tile(&self, mov: &Move) -> PlacedTile<'_> {
+        PlacedTile::new(mov.position, self.tile(mov.tile), 0)
+    }
+
     pub fn count_solved_tasks(&self) -> usize {
+        self.placements()
+            .iter()
+            .map(|p| p.solved.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the sum of the moon track costs of the placed tiles.
+    pub fn spent_cost(&self) -> usize {
+        self.placements()
+            .iter()
+            .map(|p| self.tile(p.tile).cost)
+            .sum()
+    }
+
+    /// Sums up the fraction of the required colors of every unsolved task which still can be
+    /// solved, because its tile has a free adjacent position.
+    pub fn task_progress(&self) -> f64 {
+        (0..self.num_placements)
+            .filter(|idx| {
+                self.board
+                    .position(self.placements[*idx].cell as usize)
+                    .adjacent()
+                    .iter()
+                    .any(|p| self.board.cell(*p).is_some() && self.board.get(*p).is_none())
+            })
+            .map(|idx| {
+                let placement = self.placements[idx];
+                let counts = self.count_adjacent_colors(idx);
+                self.tile(placement.tile)
+                    .tasks
+                    .iter()
+                    .enumerate()
+                    .filter(|(t, _)| placement.solved & (1 << t) == 0)
+                    .map(|(_, task)| task.progress_by(&counts))
+                    .sum::<f64>()
+            })
+            .sum()
+    }
+
+    /// Returns the smallest key of all symmetric copies of the board, shifted to start at the
+    /// origin. Boards with the same key solve the same tasks and can be completed in the same
+    /// ways.
+    pub(crate) fn canonical_key(&self) -> BoardKey {
+        canonical_key(self.history())
+    }
+
+    /// Returns the lowest corner of the rectangle around the placed tiles, which moves along with
+    /// the board when it is shifted.
+    pub(crate) fn corner(&self) -> Position {
+        if self.num_placements == 0 {
+            return Position(0, 0);
+        }
+        let (x, y) = self.corner;
         self.board
+            .position(self.board.cell_at((x as usize, y as usize)))
+    }
+
+    /// Returns the placed tiles relative to the [lowest corner](State::corner), ordered by their
+    /// position, which are the same for all shifted copies of the board.
+    pub(crate) fn shifted_key(&self) -> BoardKey {
+        let corner = self.corner();
+        let mut key: BoardKey = self
+            .history()
+            .map(|m| (m.position.0 - corner.0, m.position.1 - corner.1, m.tile))
+            .collect();
+        key.sort_unstable();
+        key
+    }
+
+    /// Returns every move of an available tile to a free position.
+    fn all_moves(&self) -> Vec<Move> {
+        let empty_positions = self.free_positions();
+        self.affordable_tiles()
+            .flat_map(|id| empty_positions.iter().map(move |pos| Move::new(id, *pos)))
+            .collect()
+    }
+
+    fn placements(&self) -> &[Placement] {
+        &self.placements[..self.num_placements]
+    }
+
+    fn color(&self, placement: usize) -> Color {
+        self.tile(self.placements[placement].tile).color
+    }
+
+    /// Places the tile of the move, if the tile is still available and fits into the budget and
+    /// the position is free and adjacent to the already placed tiles.
+    pub fn try_make_move(&mut self, mov: &Move) -> Result<(), SolverError> {
+        let available = self
+            .available
+            .get(mov.tile.0 as usize)
+            .copied()
+            .unwrap_or(0)
+            > 0
+            && self
+                .max_cost()
+                .is_some_and(|max_cost| self.tile(mov.tile).cost <= max_cost);
+        let legal_position = self.board.get(mov.position).is_none()
+            && (self.num_placements == 0
+                || mov
+                    .position
+                    .adjacent()
+                    .iter()
+                    .any(|p| self.board.get(*p).is_some()));
+        if self.num_placements == 0 {
+            self.board.center_on(mov.position);
+        }
+        match self.board.cell(mov.position) {
+            Some(cell) if available && legal_position => {
+                self.available[mov.tile.0 as usize] -= 1;
+                self.place(cell, mov.tile);
+                Ok(())
+            }
+            _ => Err(self.illegal_move(mov)),
+        }
+    }
+
+    /// Returns the error of the illegal move, which describes the tile by its contents.
+    pub(crate) fn illegal_move(&self, mov: &Move) -> SolverError {
+        SolverError::IllegalMove {
+            tile: self.tiles.get(mov.tile.index()).cloned(),
+            position: mov.position,
+        }
+    }
+
+    fn place(&mut self, cell: usize, tile: TileId) {
+        let placed = self.num_placements;
+        self.num_placements += 1;
+        self.placements[placed] = Placement {
+            cell: cell as u32,
+            tile,
+            corner: self.corner,
+            ..Placement::default()
+        };
+        self.board.set_cell(cell, placed);
+        self.components.insert(placed);
+        let (x, y) = self.board.coordinates(cell);
+        self.keys = zobrist::add(
+            self.keys,
+            zobrist::key(self.tile_keys[tile.index()], (x, y)),
+        );
+        self.corner = if placed == 0 {
+            (x as u16, y as u16)
+        } else {
+            (self.corner.0.min(x as u16), self.corner.1.min(y as u16))
+        };
+
+        let color = self.tile(tile).color;
+        for p in self.board.position(cell).adjacent() {
+            if let Some(neighbor) = self.board.get(p) {
+                if self.color(neighbor) == color {
+                    if let Some(child) = self.components.union(placed, neighbor) {
+                        let placement = &mut self.placements[placed];
+                        placement.merged[placement.num_merged as usize] = child as u8;
+                        placement.num_merged += 1;
+                    }
+                }
+            }
+        }
+
+        // Only the color group of the placed tile has changed, so only tasks of tiles adjacent
+        // to this group can have become solved.
+        let changed = self.components.find(placed);
+        for idx in 0..self.num_placements {
+            let placement = self.placements[idx];
+            let tasks = &self.tile(placement.tile).tasks;
+            let candidate = idx == placed
+                || tasks
+                    .iter()
+                    .enumerate()
+                    .any(|(t, task)| placement.solved & (1 << t) == 0 && task.has_color(color))
+                    && self
+                        .board
+                        .position(placement.cell as usize)
+                        .adjacent()
+                        .iter()
+                        .filter_map(|p| self.board.get(*p))
+                        .any(|n| self.color(n) == color && self.components.find(n) == changed);
+            if candidate {
+                let counts = self.count_adjacent_colors(idx);
+                let solved = tasks
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, task)| task.is_solved_by(&counts))
+                    .fold(placement.solved, |solved, (t, _)| solved | (1 << t));
+                self.placements[idx].solved = solved;
+            }
+        }
+    }
+
+    /// Takes back the last placed tile and returns its move, restoring the board, the remaining
+    /// tiles and the solved tasks to the state before the move.
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let placed = self.num_placements.checked_sub(1)?;
+        let placement = self.placements[placed];
+
+        // Like in `place`, only tasks of tiles adjacent to the color group of the tile can have
+        // been solved by it.
+        let color = self.color(placed);
+        let changed = self.components.find(placed);
+        let affected: u128 = (0..placed)
+            .filter(|idx| {
+                let p = self.placements[*idx];
+                self.tile(p.tile)
+                    .tasks
+                    .iter()
+                    .enumerate()
+                    .any(|(t, task)| p.solved & (1 << t) != 0 && task.has_color(color))
+                    && self
+                        .board
+                        .position(p.cell as usize)
+                        .adjacent()
+                        .iter()
+                        .filter_map(|pos| self.board.get(*pos))
+                        .any(|n| self.color(n) == color && self.components.find(n) == changed)
+            })
+            .fold(0, |affected, idx| affected | (1 << idx));
+
+        for child in placement.merged[..placement.num_merged as usize]
+            .iter()
+            .rev()
+        {
+            self.components.split(*child as usize);
+        }
+        self.board.clear_cell(placement.cell as usize);
+        self.keys = zobrist::sub(
+            self.keys,
+            zobrist::key(
+                self.tile_keys[placement.tile.index()],
+                self.board.coordinates(placement.cell as usize),
+            ),
+        );
+        self.corner = placement.corner;
+        self.available[placement.tile.index()] += 1;
+        self.placements[placed] = Placement::default();
+        self.num_placements = placed;
+
+        // A task solved on the smaller board stays solved on the larger one, so the tasks which
+        // are still solved are the ones solved by the adjacent groups without the tile.
+        for idx in (0..placed).filter(|idx| affected & (1 << idx) != 0) {
+            let solved = self.placements[idx].solved;
+            let counts = self.count_adjacent_colors(idx);
+            let tasks = &self.tile(self.placements[idx].tile).tasks;
+            self.placements[idx].solved = tasks
+                .iter()
+                .enumerate()
+                .filter(|(t, task)| solved & (1 << t) != 0 && task.is_solved_by(&counts))
+                .fold(0, |solved, (t, _)| solved | (1 << t));
+        }
+        Some(Move::new(
+            placement.tile,
+            self.board.position(placement.cell as usize),
+        ))
+    }
+
+    /// Returns the moves which placed the tiles, in the order they were made.
+    pub fn history(&self) -> impl Iterator<Item = Move> + '_ {
+        self.placements()
             .iter()
-            .flat_map(|(_pos, tile)| tile.tasks.iter())
-            .filter(|tile| tile.solved)
-            .count()
+            .map(move |p| Move::new(p.tile, self.board.position(p.cell as usize)))
+    }
+
+    /// Counts the tiles of each color in the groups adjacent to the placement, with the same
+    /// results as the search in [`Task::is_solved`].
+    fn count_adjacent_colors(&self, placement: usize) -> [usize; 4] {
+        let pos = self
+            .board
+            .position(self.placements[placement].cell as usize);
+        let color = self.color(placement);
+        let mut counts = [0; 4];
+        let mut same_color = [0; 4];
+        let mut num_same_color = 0;
+        for p in pos.adjacent() {
+            if let Some(neighbor) = self.board.get(p) {
+                if self.color(neighbor) == color {
+                    same_color[num_same_color] = neighbor;
+                    num_same_color += 1;
+                } else {
+                    counts[self.color(neighbor) as usize] += self.components.size(neighbor);
+                }
+            }
+        }
+        counts[color as usize] += match num_same_color {
+            0 => 0,
+            // The group of the only neighbor of the same color is the group of the tile itself.
+            1 => self.components.size(placement) - 1,
+            // Neighbors of the same color may be connected around the tile, which the search
+            // counts once per neighbor.
+            _ => same_color[..num_same_color]
+                .iter()
+                .map(|n| self.group_size_without(*n, placement))
+                .sum(),
+        };
+        counts
+    }
+
+    /// Counts the tiles of the color group of the start placement without passing through the
+    /// excluded placement.
+    fn group_size_without(&self, start: usize, excluded: usize) -> usize {
+        let color = self.color(start);
+        let mut visited = [false; MAX_TILES];
+        visited[excluded] = true;
+        visited[start] = true;
+        let mut unvisited = vec![start];
+        let mut size = 0;
+        while let Some(placement) = unvisited.pop() {
+            size += 1;
+            for p in self
+                .board
+                .position(self.placements[placement].cell as usize)
+                .adjacent()
+            {
+                if let Some(n) = self.board.get(p) {
+                    if !visited[n] && self.color(n) == color {
+                        visited[n] = true;
+                        unvisited.push(n);
+                    }
+                }
+            }
+        }
+        size
     }
 }
 
@@ -182,15 +987,9 @@ impl Serialize for State {
     where
         S: Serializer,
     {
-        let mut seq = serializer.serialize_seq(Some(self.board.len()))?;
-
-        #[derive(Serialize)]
-        struct PositionAndTile<'a> {
-            position: &'a Position,
-            tile: &'a Tile,
-        }
-        for (position, tile) in &self.board {
-            seq.serialize_element(&PositionAndTile { position, tile })?;
+        let mut seq = serializer.serialize_seq(Some(self.num_placements))?;
+        for placed_tile in self.placed_tiles() {
+            seq.serialize_element(&placed_tile)?;
         }
         seq.end()
     }
@@ -203,67 +1002,79 @@ impl GameState for State {
 
     fn current_player(&self) -> Self::Player {}
 
+    /// Returns the moves of the available tiles to the free positions. In the first plies, only
+    /// the first of the moves leading to shifted, rotated or mirrored copies of the same board is
+    /// returned, as the copies reach the same scores.
     fn available_moves(&self) -> Vec<Move> {
-        let empty_positions = if self.board.is_empty() {
-            vec![Position(0, 0)]
-        } else {
-            self.board
-                .iter()
-                .flat_map(|(pos, _)| (*pos).adjacent())
-                .filter(|pos| !self.board.contains_key(pos))
-                .collect()
-        };
-
-        let set: HashSet<Move> = self
-            .tiles
-            .iter()
-            .flat_map(|t| empty_positions.iter().copied().zip(iter::repeat(t)))
-            .map(|(pos, tile): (Position, &Tile)| Move::new(tile.clone(), pos))
-            .collect();
-
-        set.into_iter().collect()
+        let moves = self.all_moves();
+        if self.num_placements == 0 || self.num_placements >= SYMMETRY_PLIES {
+            return moves;
+        }
+        let mut boards = HashSet::new();
+        moves
+            .into_iter()
+            .filter(|m| boards.insert(canonical_key(self.history().chain([*m]))))
+            .collect()
     }
 
     fn make_move(&mut self, mov: &Self::Move) {
-        let idx = self
-            .tiles
-            .iter()
-            .position(|t| *t == mov.tile)
-            .expect("cannot find played tile");
-        self.tiles.remove(idx);
-        self.board.insert(mov.position, mov.tile.clone());
+        self.try_make_move(mov)
+            .expect("search must only play available moves");
+    }
+}
 
-        for (pos, tile) in self.board.clone() {
-            for (idx, task) in tile.tasks.iter().enumerate() {
-                if !task.solved
-                    && (pos == mov.position || task.has_color(mov.tile.color))
-                    && task.is_solved(pos, &self.board)
-                {
-                    self.board.get_mut(&pos).unwrap().tasks[idx].solved = true;
-                }
+/// Returns the smallest key of all symmetric copies of the board of the moves.
+fn canonical_key(moves: impl Iterator<Item = Move>) -> BoardKey {
+    let moves: Vec<Move> = moves.collect();
+    SYMMETRIES
+        .iter()
+        .map(|symmetry| {
+            let mut key: BoardKey = moves
+                .iter()
+                .map(|m| {
+                    let (x, y) = symmetry(m.position.0, m.position.1);
+                    (x, y, m.tile)
+                })
+                .collect();
+            let min_x = key.iter().map(|t| t.0).min().unwrap_or(0);
+            let min_y = key.iter().map(|t| t.1).min().unwrap_or(0);
+            for t in &mut key {
+                t.0 -= min_x;
+                t.1 -= min_y;
             }
-        }
-    }
+            key.sort_unstable();
+            key
+        })
+        .min()
+        .expect("there are symmetries")
 }
 
+/// Hashes the placed tiles by their positions relative to the [lowest corner](State::corner),
+/// so shifted copies of a board share their hash. The hash is updated with every move and is the
+/// same in every build. The remaining tiles are not hashed, as they follow from the placed tiles
+/// in the states of one game.
+///
+/// Rotated and mirrored copies of a board get different hashes, so they do not share a node of
+/// the transposition table: the moves stored in a node are only valid in the orientation of its
+/// board. Such copies are only left out by [`State::available_moves`] in the first
+/// `SYMMETRY_PLIES` plies.
 impl TranspositionHash for State {
     fn hash(&self) -> u64 {
-        let mut h: u64 = 0;
-        for elt in &self.board {
-            let mut hasher = DefaultHasher::new();
-            elt.hash(&mut hasher);
-            h ^= hasher.finish();
-        }
-        let mut hasher = DefaultHasher::new();
-        self.tiles.hash(&mut hasher);
-        h ^= hasher.finish();
-        h
+        let (x, y) = self.corner;
+        zobrist::normalize(self.keys, (x as usize, y as usize))
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    fn mov(state: &State, tile: &Tile, position: Position) -> super::Move {
+        super::Move::new(state.tile_id(tile).unwrap(), position)
+    }
 
     #[test]
     fn empty_state() {
@@ -277,7 +1088,7 @@ mod test {
         let state = State::with_tiles(vec![tile.clone()]);
         let moves = state.available_moves();
         assert_eq!(1, moves.len());
-        assert_eq!(super::Move::new(tile, Position(0, 0)), moves[0]);
+        assert_eq!(mov(&state, &tile, Position(0, 0)), moves[0]);
     }
 
     #[test]
@@ -285,15 +1096,16 @@ mod test {
         let tile = Tile::new(1, Color::Yellow, vec![]);
         let mut state = State::with_tiles(vec![tile.clone(), tile.clone()]);
 
-        state.make_move(&super::Move::new(tile.clone(), Position(0, 0)));
+        state.make_move(&mov(&state, &tile, Position(0, 0)));
 
-        let moves = state.available_moves();
+        assert_eq!(1, state.available_moves().len(), "all moves are symmetric");
+        let moves = state.all_moves();
 
         assert_eq!(4, moves.len());
-        assert!(moves.contains(&super::Move::new(tile.clone(), Position(1, 0))));
-        assert!(moves.contains(&super::Move::new(tile.clone(), Position(0, 1))));
-        assert!(moves.contains(&super::Move::new(tile.clone(), Position(-1, 0))));
-        assert!(moves.contains(&super::Move::new(tile.clone(), Position(0, -1))));
+        assert!(moves.contains(&mov(&state, &tile, Position(1, 0))));
+        assert!(moves.contains(&mov(&state, &tile, Position(0, 1))));
+        assert!(moves.contains(&mov(&state, &tile, Position(-1, 0))));
+        assert!(moves.contains(&mov(&state, &tile, Position(0, -1))));
     }
 
     #[test]
@@ -301,19 +1113,20 @@ mod test {
         let tile = Tile::new(1, Color::Yellow, vec![]);
         let mut state = State::with_tiles(vec![tile.clone(), tile.clone(), tile.clone()]);
 
-        state.make_move(&super::Move::new(tile.clone(), Position(0, 0)));
-        state.make_move(&super::Move::new(tile.clone(), Position(1, 0)));
+        state.make_move(&mov(&state, &tile, Position(0, 0)));
+        state.make_move(&mov(&state, &tile, Position(1, 0)));
 
-        let moves = state.available_moves();
+        assert_eq!(2, state.available_moves().len(), "at an end or at a side");
+        let moves = state.all_moves();
 
         assert_eq!(6, moves.len());
 
-        assert!(moves.contains(&super::Move::new(tile.clone(), Position(2, 0))));
-        assert!(moves.contains(&super::Move::new(tile.clone(), Position(1, 1))));
-        assert!(moves.contains(&super::Move::new(tile.clone(), Position(1, -1))));
-        assert!(moves.contains(&super::Move::new(tile.clone(), Position(0, 1))));
-        assert!(moves.contains(&super::Move::new(tile.clone(), Position(-1, 0))));
-        assert!(moves.contains(&super::Move::new(tile.clone(), Position(0, -1))));
+        assert!(moves.contains(&mov(&state, &tile, Position(2, 0))));
+        assert!(moves.contains(&mov(&state, &tile, Position(1, 1))));
+        assert!(moves.contains(&mov(&state, &tile, Position(1, -1))));
+        assert!(moves.contains(&mov(&state, &tile, Position(0, 1))));
+        assert!(moves.contains(&mov(&state, &tile, Position(-1, 0))));
+        assert!(moves.contains(&mov(&state, &tile, Position(0, -1))));
     }
 
     #[test]
@@ -328,17 +1141,18 @@ mod test {
             tile2.clone(),
         ]);
 
-        state.make_move(&super::Move::new(tile1.clone(), Position(0, 0)));
-        state.make_move(&super::Move::new(tile1.clone(), Position(1, 0)));
+        state.make_move(&mov(&state, &tile1, Position(0, 0)));
+        state.make_move(&mov(&state, &tile1, Position(1, 0)));
 
-        let moves = state.available_moves();
+        assert_eq!(4, state.available_moves().len());
+        let moves = state.all_moves();
 
         assert_eq!(12, moves.len());
 
-        assert!(moves.contains(&super::Move::new(tile1.clone(), Position(2, 0))));
-        assert!(moves.contains(&super::Move::new(tile1.clone(), Position(1, 1))));
-        assert!(moves.contains(&super::Move::new(tile2.clone(), Position(2, 0))));
-        assert!(moves.contains(&super::Move::new(tile2.clone(), Position(1, 1))));
+        assert!(moves.contains(&mov(&state, &tile1, Position(2, 0))));
+        assert!(moves.contains(&mov(&state, &tile1, Position(1, 1))));
+        assert!(moves.contains(&mov(&state, &tile2, Position(2, 0))));
+        assert!(moves.contains(&mov(&state, &tile2, Position(1, 1))));
     }
 
     #[test]
@@ -353,21 +1167,21 @@ mod test {
             tile2.clone(),
         ]);
 
-        state.make_move(&super::Move::new(tile1.clone(), Position(0, 0)));
-        state.make_move(&super::Move::new(tile1.clone(), Position(1, 0)));
-        state.make_move(&super::Move::new(tile1.clone(), Position(0, -1)));
+        state.make_move(&mov(&state, &tile1, Position(0, 0)));
+        state.make_move(&mov(&state, &tile1, Position(1, 0)));
+        state.make_move(&mov(&state, &tile1, Position(0, -1)));
 
         let moves = state.available_moves();
 
         assert_eq!(7, moves.len());
 
-        assert!(moves.contains(&super::Move::new(tile2.clone(), Position(2, 0))));
-        assert!(moves.contains(&super::Move::new(tile2.clone(), Position(1, 1))));
-        assert!(moves.contains(&super::Move::new(tile2.clone(), Position(1, 1))));
-        assert!(moves.contains(&super::Move::new(tile2.clone(), Position(1, -1))));
-        assert!(moves.contains(&super::Move::new(tile2.clone(), Position(0, 1))));
-        assert!(moves.contains(&super::Move::new(tile2.clone(), Position(-1, -1))));
-        assert!(moves.contains(&super::Move::new(tile2.clone(), Position(0, -2))));
+        assert!(moves.contains(&mov(&state, &tile2, Position(2, 0))));
+        assert!(moves.contains(&mov(&state, &tile2, Position(1, 1))));
+        assert!(moves.contains(&mov(&state, &tile2, Position(1, 1))));
+        assert!(moves.contains(&mov(&state, &tile2, Position(1, -1))));
+        assert!(moves.contains(&mov(&state, &tile2, Position(0, 1))));
+        assert!(moves.contains(&mov(&state, &tile2, Position(-1, -1))));
+        assert!(moves.contains(&mov(&state, &tile2, Position(0, -2))));
     }
 
     #[test]
@@ -377,14 +1191,15 @@ mod test {
 
         let mut state = State::with_tiles(vec![tile1.clone(), tile2.clone()]);
 
-        state.make_move(&super::Move::new(tile1.clone(), Position(0, 0)));
-        state.make_move(&super::Move::new(tile2.clone(), Position(1, 0)));
+        state.make_move(&mov(&state, &tile1, Position(0, 0)));
+        state.make_move(&mov(&state, &tile2, Position(1, 0)));
 
-        let option = state.board.get(&Position(1, 0));
+        let board = state.tiles_by_position();
+        let option = board.get(&Position(1, 0));
         assert!(option.is_some());
         let tile = option.unwrap();
 
-        let option = tile.tasks.get(0);
+        let option = tile.tasks.first();
         assert!(option.is_some());
         let task = option.unwrap();
 
@@ -406,16 +1221,17 @@ mod test {
 
         let mut state = State::with_tiles(vec![tile1.clone(), tile1.clone(), tile2.clone()]);
 
-        state.make_move(&super::Move::new(tile1.clone(), Position(0, 0)));
-        state.make_move(&super::Move::new(tile1.clone(), Position(1, 0)));
-        state.make_move(&super::Move::new(tile2.clone(), Position(2, 0)));
+        state.make_move(&mov(&state, &tile1, Position(0, 0)));
+        state.make_move(&mov(&state, &tile1, Position(1, 0)));
+        state.make_move(&mov(&state, &tile2, Position(2, 0)));
 
-        let tile = state.board.get(&Position(2, 0)).unwrap();
-        let task = tile.tasks.get(0).unwrap();
+        let board = state.tiles_by_position();
+        let tile = board.get(&Position(2, 0)).unwrap();
+        let task = tile.tasks.first().unwrap();
         assert!(task.solved);
 
-        let tile = state.board.get(&Position(0, 0)).unwrap();
-        let task = tile.tasks.get(0).unwrap();
+        let tile = board.get(&Position(0, 0)).unwrap();
+        let task = tile.tasks.first().unwrap();
         assert!(!task.solved);
 
         assert_eq!(1, state.count_solved_tasks())
@@ -431,10 +1247,378 @@ mod test {
 
         let mut state = State::with_tiles(vec![tile.clone(), tile.clone(), tile.clone()]);
 
-        state.make_move(&super::Move::new(tile.clone(), Position(0, 0)));
-        state.make_move(&super::Move::new(tile.clone(), Position(1, 0)));
-        state.make_move(&super::Move::new(tile.clone(), Position(2, 0)));
+        state.make_move(&mov(&state, &tile, Position(0, 0)));
+        state.make_move(&mov(&state, &tile, Position(1, 0)));
+        state.make_move(&mov(&state, &tile, Position(2, 0)));
+
+        assert_eq!(0, state.count_solved_tasks());
+    }
+
+    #[test]
+    fn illegal_moves() {
+        let tile1 = Tile::new(1, Color::Teal, vec![]);
+        let tile2 = Tile::new(2, Color::Blue, vec![]);
+
+        let mut state = State::with_tiles(vec![tile1.clone(), tile1.clone()]);
+
+        assert_eq!(None, state.tile_id(&tile2));
+        assert!(state
+            .try_make_move(&super::Move::new(TileId(1), Position(0, 0)))
+            .is_err());
+        assert!(state
+            .try_make_move(&mov(&state, &tile1, Position(0, 0)))
+            .is_ok());
+        assert!(state
+            .try_make_move(&mov(&state, &tile1, Position(0, 0)))
+            .is_err());
+        assert!(state
+            .try_make_move(&mov(&state, &tile1, Position(2, 0)))
+            .is_err());
+        assert_eq!(1, state.remaining_tiles().count());
+    }
+
+    #[test]
+    fn moves_stay_on_board() {
+        let tile = Tile::new(1, Color::Teal, vec![]);
+        let mut state = State::with_tiles(vec![tile.clone(); 40]);
+
+        for x in 0..39 {
+            state.make_move(&mov(&state, &tile, Position(1000 + x, -1000)));
+        }
+
+        let moves = state.available_moves();
+        assert_eq!(2 * 39 + 2, moves.len());
+        assert!(moves.contains(&mov(&state, &tile, Position(999, -1000))));
+        assert!(moves.contains(&mov(&state, &tile, Position(1039, -1000))));
+        state.make_move(&mov(&state, &tile, Position(1039, -1000)));
+        assert!(state.available_moves().is_empty());
+        assert_eq!(
+            Position(1039, -1000),
+            state.history().last().unwrap().position()
+        );
+    }
+
+    #[test]
+    fn clones_are_independent() {
+        let tile = Tile::new(1, Color::Teal, vec![Task::new(vec![Color::Teal])]);
+        let mut state = State::with_tiles(vec![tile.clone(); 2]);
+        state.make_move(&mov(&state, &tile, Position(0, 0)));
+
+        let mut clone = state.clone();
+        clone.make_move(&mov(&clone, &tile, Position(1, 0)));
+
+        assert_eq!(1, state.placed_tiles().count());
+        assert_eq!(0, state.count_solved_tasks());
+        assert_eq!(2, clone.count_solved_tasks());
+    }
+
+    /// Places the tile like `make_move` did before the color components were introduced, by
+    /// searching the groups of every tile on the board.
+    fn make_move_by_search(board: &mut HashMap<Position, Tile>, placed: &Tile, position: Position) {
+        board.insert(position, placed.clone());
+        for (pos, tile) in board.clone() {
+            for (idx, task) in tile.tasks.iter().enumerate() {
+                if !task.solved
+                    && (pos == position || task.has_color(placed.color))
+                    && task.is_solved(pos, board)
+                {
+                    board.get_mut(&pos).unwrap().tasks[idx].solved = true;
+                }
+            }
+        }
+    }
+
+    fn random_tile(rng: &mut StdRng) -> Tile {
+        let colors = [Color::Blue, Color::Yellow, Color::Teal, Color::Red];
+        let tasks = (0..rng.gen_range(1..=3))
+            .map(|_| {
+                Task::new(
+                    (0..rng.gen_range(1..=4))
+                        .map(|_| *colors.choose(rng).unwrap())
+                        .collect(),
+                )
+            })
+            .collect();
+        Tile::new(rng.gen_range(1..=7), *colors.choose(rng).unwrap(), tasks)
+    }
+
+    #[test]
+    fn incremental_solving_equals_search() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..200 {
+            let tiles: Vec<Tile> = (0..rng.gen_range(1..=20))
+                .map(|_| random_tile(&mut rng))
+                .collect();
+            let mut state = State::with_tiles(tiles);
+            let mut board = HashMap::new();
+
+            while let Some(m) = state.available_moves().choose(&mut rng).cloned() {
+                let tile = state.tile(m.tile).clone();
+                state.make_move(&m);
+                make_move_by_search(&mut board, &tile, m.position);
+                assert_eq!(board, state.tiles_by_position());
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_solving_counts_groups_around_tile() {
+        let teal = Tile::new(1, Color::Teal, vec![]);
+        let center = Tile::new(
+            1,
+            Color::Teal,
+            vec![
+                Task::new(vec![Color::Teal; 4]),
+                Task::new(vec![Color::Teal; 7]),
+            ],
+        );
+        let mut tiles = vec![teal.clone(); 3];
+        tiles.push(center.clone());
+        let mut state = State::with_tiles(tiles);
+        let mut board = HashMap::new();
+
+        for (tile, pos) in [
+            (&teal, Position(1, 0)),
+            (&teal, Position(1, 1)),
+            (&teal, Position(0, 1)),
+            (&center, Position(0, 0)),
+        ] {
+            state.make_move(&mov(&state, tile, pos));
+            make_move_by_search(&mut board, tile, pos);
+        }
 
+        assert_eq!(board, state.tiles_by_position());
+        assert_eq!(1, state.count_solved_tasks());
+    }
+
+    #[test]
+    fn unmake_move_restores_state() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..200 {
+            let tiles: Vec<Tile> = (0..rng.gen_range(1..=20))
+                .map(|_| random_tile(&mut rng))
+                .collect();
+            let mut state = State::with_tiles(tiles);
+            let original = TranspositionHash::hash(&state);
+            let mut snapshots = vec![];
+
+            for _ in 0..40 {
+                let moves = state.available_moves();
+                if moves.is_empty() || (!snapshots.is_empty() && rng.gen_bool(0.3)) {
+                    let (hash, board, moves): (u64, BoardTiles, Vec<Move>) =
+                        snapshots.pop().unwrap();
+                    assert!(state.unmake_move().is_some());
+                    assert_eq!(hash, TranspositionHash::hash(&state));
+                    assert_eq!(board, state.board_tiles());
+                    assert_eq!(moves, state.available_moves());
+                } else {
+                    snapshots.push((
+                        TranspositionHash::hash(&state),
+                        state.board_tiles(),
+                        moves.clone(),
+                    ));
+                    state.make_move(moves.choose(&mut rng).unwrap());
+                }
+            }
+
+            while state.unmake_move().is_some() {}
+            assert_eq!(original, TranspositionHash::hash(&state));
+            assert_eq!(0, state.placed_tiles().count());
+        }
+    }
+
+    #[test]
+    fn history() {
+        let yellow = Tile::new(1, Color::Yellow, vec![Task::new(vec![Color::Red])]);
+        let red = Tile::new(2, Color::Red, vec![]);
+        let mut state = State::with_tiles(vec![yellow.clone(), red.clone()]);
+        assert_eq!(None, state.unmake_move());
+
+        let moves = vec![
+            mov(&state, &yellow, Position(0, 0)),
+            mov(&state, &red, Position(0, 1)),
+        ];
+        for m in &moves {
+            state.make_move(m);
+        }
+        assert_eq!(moves, state.history().collect::<Vec<_>>());
+        assert_eq!(1, state.count_solved_tasks());
+
+        let clone = state.clone();
+        assert_eq!(Some(moves[1]), state.unmake_move());
         assert_eq!(0, state.count_solved_tasks());
+        assert_eq!(1, state.available(moves[1].tile()));
+        assert_eq!(vec![moves[0]], state.history().collect::<Vec<_>>());
+        assert_eq!(2, clone.history().count(), "clones keep their own history");
+    }
+
+    #[test]
+    fn budget() {
+        let cheap = Tile::new(1, Color::Red, vec![]);
+        let expensive = Tile::new(5, Color::Blue, vec![]);
+        let tiles = vec![cheap.clone(), cheap.clone(), expensive.clone()];
+
+        let mut state = State::with_budget(tiles.clone(), TileBudget::Count(2));
+        state.make_move(&mov(&state, &expensive, Position(0, 0)));
+        state.make_move(&mov(&state, &cheap, Position(1, 0)));
+        assert!(state.available_moves().is_empty());
+        assert_eq!(1, state.remaining_tiles().count());
+
+        let mut state = State::with_budget(tiles, TileBudget::Cost(6));
+        state.make_move(&mov(&state, &cheap, Position(0, 0)));
+        state.make_move(&mov(&state, &cheap, Position(1, 0)));
+        assert_eq!(Some(4), state.max_cost());
+        assert!(state.available_moves().is_empty());
+        assert!(matches!(
+            state.try_make_move(&mov(&state, &expensive, Position(2, 0))),
+            Err(SolverError::IllegalMove { .. })
+        ));
+        state.unmake_move();
+        assert_eq!(Some(5), state.max_cost());
+        assert_eq!(2, state.all_moves().len() / 4, "both tiles fit");
+
+        state.set_remaining_budget(TileBudget::Cost(4));
+        assert_eq!(Some(TileBudget::Cost(5)), state.budget());
+        assert_eq!(Some(4), state.max_cost());
+        state.set_remaining_budget(TileBudget::Count(0));
+        assert!(state.available_moves().is_empty());
+
+        for budget in [TileBudget::Count(3), TileBudget::Cost(12)] {
+            assert_eq!(Ok(budget), budget.to_string().parse());
+        }
+        assert!("count".parse::<TileBudget>().is_err());
+        assert!("tokens:3".parse::<TileBudget>().is_err());
+    }
+
+    /// Counts the states of the game tree and finds the most tasks a complete board solves.
+    fn game_tree(state: &mut State, moves_fn: fn(&State) -> Vec<Move>) -> (usize, usize) {
+        let moves = moves_fn(state);
+        if moves.is_empty() {
+            return (1, state.count_solved_tasks());
+        }
+        let (mut size, mut best) = (1, 0);
+        for m in moves {
+            state.make_move(&m);
+            let (subtree, solved) = game_tree(state, moves_fn);
+            state.unmake_move();
+            size += subtree;
+            best = best.max(solved);
+        }
+        (size, best)
+    }
+
+    #[test]
+    fn symmetric_moves() {
+        let tiles = crate::parse_file("../resources/tiles.json").unwrap();
+        let sizes: Vec<(usize, usize)> = (2..=4)
+            .map(|n| {
+                let mut state = State::with_tiles(tiles[..n].to_vec());
+                let (all, best) = game_tree(&mut state, State::all_moves);
+                let (reduced, reduced_best) = game_tree(&mut state, State::available_moves);
+                assert_eq!(best, reduced_best, "{} tiles", n);
+                (reduced, all)
+            })
+            .collect();
+        assert_eq!(vec![(5, 11), (34, 172), (833, 4853)], sizes);
+
+        let red = Tile::new(1, Color::Red, vec![]);
+        let blue = Tile::new(1, Color::Blue, vec![]);
+        let mut state = State::with_tiles(vec![red.clone(), blue.clone(), blue.clone()]);
+        state.make_move(&mov(&state, &red, Position(0, 0)));
+        state.make_move(&mov(&state, &blue, Position(0, 1)));
+        let moves = state.available_moves();
+        assert_eq!(4, moves.len(), "in line or beside, at either tile");
+        assert_eq!(
+            moves[0].position(),
+            state.all_moves()[0].position(),
+            "the first of the symmetric moves is kept"
+        );
+        let copy = state.clone();
+        state.make_move(&mov(&state, &blue, Position(1, 1)));
+        let mut mirrored = copy;
+        mirrored.make_move(&mov(&mirrored, &blue, Position(-1, 1)));
+        assert_eq!(state.canonical_key(), mirrored.canonical_key());
+    }
+
+    #[test]
+    fn hash_of_shifted_boards() {
+        let red = Tile::new(1, Color::Red, vec![]);
+        let blue = Tile::new(1, Color::Blue, vec![]);
+        let tiles = vec![red.clone(), blue.clone(), blue.clone()];
+        let board = |moves: &[(&Tile, Position)]| {
+            let mut state = State::with_tiles(tiles.clone());
+            for (tile, position) in moves {
+                state.make_move(&mov(&state, tile, *position));
+            }
+            TranspositionHash::hash(&state)
+        };
+
+        let hash = board(&[(&red, Position(0, 0)), (&blue, Position(1, 0))]);
+        assert_eq!(
+            hash,
+            board(&[(&blue, Position(0, 0)), (&red, Position(-1, 0))]),
+            "shifted by one column"
+        );
+        assert_ne!(
+            hash,
+            board(&[(&red, Position(0, 0)), (&blue, Position(-1, 0))])
+        );
+        assert_ne!(
+            hash,
+            board(&[(&red, Position(0, 0)), (&blue, Position(0, 1))])
+        );
+        assert_eq!(
+            0x0f41_0909_9b69_9a8d, hash,
+            "the hash is the same in every build"
+        );
+    }
+
+    #[test]
+    fn hash_of_tiles_in_any_order() {
+        let red = Tile::new(1, Color::Red, vec![Task::new(vec![Color::Blue])]);
+        let blue = Tile::new(2, Color::Blue, vec![Task::new(vec![Color::Red])]);
+        let board = |tiles: Vec<Tile>| {
+            let mut state = State::with_tiles(tiles);
+            state.make_move(&mov(&state, &red, Position(0, 0)));
+            let hash = TranspositionHash::hash(&state);
+            state.make_move(&mov(&state, &blue, Position(-1, 0)));
+            state.make_move(&mov(&state, &red, Position(-1, -1)));
+            let full = TranspositionHash::hash(&state);
+            state.unmake_move();
+            state.unmake_move();
+            assert_eq!(hash, TranspositionHash::hash(&state));
+            full
+        };
+
+        assert_eq!(
+            board(vec![red.clone(), red.clone(), blue.clone()]),
+            board(vec![blue.clone(), red.clone(), red.clone()])
+        );
+    }
+
+    /// Visits every state of the game tree, without leaving out symmetric moves.
+    fn visit_all(state: &mut State, visit: &mut dyn FnMut(&State)) {
+        visit(state);
+        for m in state.all_moves() {
+            state.make_move(&m);
+            visit_all(state, visit);
+            state.unmake_move();
+        }
+    }
+
+    #[test]
+    fn hash_collisions() {
+        let tiles = crate::parse_file("../resources/tiles.json").unwrap();
+        let mut boards: HashMap<u64, BoardKey> = HashMap::new();
+        let mut num_states = 0;
+        visit_all(&mut State::with_tiles(tiles[..5].to_vec()), &mut |state| {
+            let board = state.shifted_key();
+            let hash = TranspositionHash::hash(state);
+            assert_eq!(&board, boards.entry(hash).or_insert_with(|| board.clone()));
+            num_states += 1;
+        });
+        assert!(num_states > 100_000, "{} states", num_states);
+        let distinct: HashSet<_> = boards.values().collect();
+        assert_eq!(boards.len(), distinct.len(), "one hash per board");
     }
 }
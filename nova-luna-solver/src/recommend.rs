@@ -0,0 +1,250 @@
+//! Recommendation of the next move for a board of a game in progress.
+
+use crate::catalog::{self, TileRef};
+use crate::error::SolverError;
+use crate::evaluator::LeafEvaluator;
+use crate::nova_luna::{BoardTiles, Move, Position, State, Tile, TileBudget};
+use crate::scoring::{Scoring, SCORE_SCALE};
+use crate::solver::SearchState;
+use crate::{validation, AnytimeSearch, SolverParameters, Strategy, Transpositions};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Board and hand of a game in progress, as read by [`parse_position`].
+#[derive(Deserialize)]
+struct PositionRef {
+    board: Vec<BoardTileRef>,
+    hand: Vec<TileRef>,
+}
+
+#[derive(Deserialize)]
+struct BoardTileRef {
+    position: Position,
+    tile: TileRef,
+}
+
+pub struct RecommendOptions {
+    /// Number of moves to recommend.
+    pub top: usize,
+    pub num_playouts: u32,
+    /// Stops the search at the time limit instead of after `num_playouts`.
+    pub time_limit: Option<Duration>,
+    pub num_threads: usize,
+    pub exploration_constant: f64,
+    pub evaluator: Arc<dyn LeafEvaluator>,
+    pub scoring: Scoring,
+    /// Limits the tiles placed from now on, e.g. to the steps the player can still advance on
+    /// the moon track.
+    pub budget: Option<TileBudget>,
+    pub seed: Option<u64>,
+}
+
+/// Move recommended by the search.
+#[derive(Clone, Debug, Serialize)]
+pub struct Recommendation {
+    pub position: Position,
+    pub tile: Tile,
+    /// Number of playouts which started with this move.
+    pub visits: u64,
+    /// Mean score of the boards the search reached after this move.
+    pub expected_score: f64,
+}
+
+/// Parses a JSON object with the placed tiles of the `board` and the tiles of the `hand`,
+/// which are given either as objects or by their ID in the catalog, e.g.
+/// `{"board": [{"position": [0, 0], "tile": 3}], "hand": [1, 7]}`.
+pub fn parse_position(input: String) -> Result<(BoardTiles, Vec<Tile>), SolverError> {
+    let position: PositionRef = serde_json::from_str(&input)?;
+    let positions: Vec<Position> = position.board.iter().map(|t| t.position).collect();
+    let num_placed = positions.len();
+    let tiles = position
+        .board
+        .into_iter()
+        .map(|t| t.tile)
+        .chain(position.hand)
+        .collect();
+    let mut tiles = catalog::resolve(tiles)?;
+    validation::check_schema(&tiles)?;
+    let hand = tiles.split_off(num_placed);
+    Ok((positions.into_iter().zip(tiles).collect(), hand))
+}
+
+/// Places the tiles of the board, starting with the first one and continuing with any tile next
+/// to the placed ones, so the board can be given in any order. The solved flags of the tasks
+/// are recomputed.
+pub fn board_state(board: &[(Position, Tile)], hand: Vec<Tile>) -> Result<State, SolverError> {
+    let board: BoardTiles = board
+        .iter()
+        .map(|(pos, tile)| (*pos, tile.with_solved_flags(0)))
+        .collect();
+    let mut tiles: Vec<Tile> = board.iter().map(|(_, t)| t.clone()).collect();
+    tiles.extend(hand);
+    validation::check_schema(&tiles)?;
+    let mut state = State::with_tiles(tiles);
+
+    let mut unplaced: Vec<Move> = board
+        .iter()
+        .map(|(pos, tile)| Move::new(state.tile_id(tile).expect("tile of the state"), *pos))
+        .collect();
+    while !unplaced.is_empty() {
+        match unplaced.iter().position(|m| state.try_make_move(m).is_ok()) {
+            Some(idx) => {
+                unplaced.remove(idx);
+            }
+            None => return Err(state.illegal_move(&unplaced[0])),
+        }
+    }
+    Ok(state)
+}
+
+/// Searches the next moves for the board, placing the tiles of the hand or choosing from them
+/// within the budget, and returns the `top` moves the search tried most.
+pub fn recommend(
+    board: &[(Position, Tile)],
+    hand: Vec<Tile>,
+    options: &RecommendOptions,
+) -> Result<Vec<Recommendation>, SolverError> {
+    let now = Instant::now();
+    let mut state = board_state(board, hand)?;
+    if let Some(budget) = options.budget {
+        state.set_remaining_budget(budget);
+    }
+    let param = SolverParameters {
+        tiles: vec![],
+        budget: None,
+        output_file: None,
+        output_dir: None,
+        print_statistics: false,
+        print_moves: false,
+        num_playouts: options.num_playouts,
+        time_limit: options.time_limit,
+        num_threads: options.num_threads,
+        debug: false,
+        exploration_constant: options.exploration_constant,
+        evaluator: options.evaluator.clone(),
+        scoring: options.scoring,
+        strategy: Strategy::Mcts,
+        transpositions: Transpositions::default(),
+        seed: options.seed,
+    };
+    let seed = options.seed.unwrap_or_else(rand::random);
+    let mut search = AnytimeSearch::with_seed(&param, state.clone(), seed, options.num_threads);
+    match options.time_limit {
+        Some(limit) => search.run_until(now + limit),
+        None => search.run_playouts(options.num_playouts),
+    }
+
+    let root = SearchState(state.clone());
+    let mut moves: Vec<Recommendation> = search
+        .mcts
+        .tree()
+        .root_node()
+        .moves()
+        .filter(|m| m.visits() > 0)
+        .map(|m| Recommendation {
+            position: root.absolute(m.get_move()).position(),
+            tile: state.tile(m.get_move().tile()).clone(),
+            visits: m.visits(),
+            expected_score: m.sum_rewards() as f64 / m.visits() as f64 / SCORE_SCALE,
+        })
+        .collect();
+    moves.sort_by(|a, b| {
+        b.visits
+            .cmp(&a.visits)
+            .then(b.expected_score.total_cmp(&a.expected_score))
+    });
+    moves.truncate(options.top);
+    Ok(moves)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::SolvedTasks;
+    use crate::nova_luna::{Color, Task};
+
+    fn options(top: usize) -> RecommendOptions {
+        RecommendOptions {
+            top,
+            num_playouts: 500,
+            time_limit: None,
+            num_threads: 1,
+            exploration_constant: 2.0,
+            evaluator: Arc::new(SolvedTasks),
+            scoring: Scoring::Tokens,
+            budget: None,
+            seed: Some(3),
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let (board, hand) = parse_position(
+            r#"{"board": [{"position": [1, 0], "tile": 2}, {"position": [0, 0], "tile": 1}],
+                "hand": [3, {"color": "Red", "cost": 2, "tasks": [["Blue"]]}]}"#
+                .to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            vec![Position::new(1, 0), Position::new(0, 0)],
+            board.iter().map(|(p, _)| *p).collect::<Vec<_>>()
+        );
+        assert_eq!(2, hand.len());
+        assert_eq!(Color::Red, hand[1].color());
+
+        // The board may be given in any order, as long as the tiles are connected.
+        let state = board_state(&board, hand.clone()).unwrap();
+        assert_eq!(2, state.placed_tiles().count());
+        assert_eq!(2, state.remaining_tiles().count());
+
+        let (board, _) = parse_position(
+            r#"{"board": [{"position": [0, 0], "tile": 2}, {"position": [2, 0], "tile": 1}],
+                "hand": []}"#
+                .to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            "illegal move: catalog tile 1 at (2, 0)",
+            board_state(&board, vec![]).err().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn recommends_solving_move() {
+        let blue = Tile::new(2, Color::Blue, vec![]);
+        let red = Tile::new(3, Color::Red, vec![Task::new(vec![Color::Blue])]);
+        let board = vec![(Position::new(0, 0), red.clone())];
+        let hand = vec![blue.clone()];
+
+        let moves = recommend(&board, hand.clone(), &options(2)).unwrap();
+        assert_eq!(1, moves.len(), "all free positions are symmetric");
+        assert_eq!(blue, moves[0].tile);
+        assert_eq!(1.0, moves[0].expected_score);
+
+        let board = vec![
+            (Position::new(0, 0), red.clone()),
+            (Position::new(1, 0), red.clone()),
+            (Position::new(1, 1), red),
+        ];
+        let moves = recommend(&board, hand, &options(10)).unwrap();
+        assert_eq!(7, moves.len(), "one move per free position");
+        assert_eq!(blue, moves[0].tile);
+        assert_eq!(2.0, moves[0].expected_score);
+        assert!(moves[0].visits >= moves[1].visits);
+    }
+
+    #[test]
+    fn recommends_affordable_tiles() {
+        let cheap = Tile::new(2, Color::Blue, vec![]);
+        let expensive = Tile::new(6, Color::Blue, vec![]);
+        let red = Tile::new(3, Color::Red, vec![Task::new(vec![Color::Blue])]);
+        let board = vec![(Position::new(0, 0), red)];
+        let mut options = options(10);
+        options.budget = Some(TileBudget::Cost(4));
+
+        let moves = recommend(&board, vec![expensive, cheap.clone()], &options).unwrap();
+        assert_eq!(1, moves.len());
+        assert!(moves.iter().all(|m| m.tile == cheap));
+    }
+}
@@ -0,0 +1,177 @@
+//! Versioned JSON document of a [`State`], which can be loaded back to resume the game.
+//!
+//! Unlike the plain list of placed tiles the state serializes to, the document also holds the
+//! remaining tiles and keeps the order in which the tiles were placed.
+
+use crate::error::SolverError;
+use crate::nova_luna::{Position, State, Tile, TileBudget};
+use crate::recommend::board_state;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// Version of the schema written by [`save_state`]. Version 2 added the budget, documents of
+/// version 1 are still loaded.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Versions of the schema which can be loaded.
+const SUPPORTED_VERSIONS: RangeInclusive<u32> = 1..=SCHEMA_VERSION;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SavedState {
+    pub version: u32,
+    /// Placed tiles in the order they were placed, with the solved flags of their tasks.
+    pub board: Vec<SavedTile>,
+    /// Tiles which have not been placed yet.
+    pub remaining: Vec<Tile>,
+    /// Budget of a game choosing from a pool of tiles, which includes the placed tiles. Added in
+    /// version 2.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<TileBudget>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SavedTile {
+    pub position: Position,
+    pub tile: Tile,
+}
+
+/// Only the version of a document, read before the rest of the document.
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+impl From<&State> for SavedState {
+    fn from(state: &State) -> SavedState {
+        SavedState {
+            version: SCHEMA_VERSION,
+            board: state
+                .board_tiles()
+                .into_iter()
+                .map(|(position, tile)| SavedTile { position, tile })
+                .collect(),
+            remaining: state.remaining_tiles().cloned().collect(),
+            budget: state.budget(),
+        }
+    }
+}
+
+impl SavedState {
+    /// Places the tiles of the board in their order. The stored solved flags are not trusted
+    /// but recomputed, [`verify_string`](crate::verify_string) reports where they differ.
+    pub fn into_state(self) -> Result<State, SolverError> {
+        if !SUPPORTED_VERSIONS.contains(&self.version) {
+            return Err(SolverError::UnsupportedVersion(self.version));
+        }
+        let board: Vec<(Position, Tile)> = self
+            .board
+            .into_iter()
+            .map(|t| (t.position, t.tile))
+            .collect();
+        let mut state = board_state(&board, self.remaining)?;
+        state.set_budget(self.budget);
+        Ok(state)
+    }
+}
+
+pub fn save_state(state: &State) -> Result<String, SolverError> {
+    Ok(serde_json::to_string(&SavedState::from(state))?)
+}
+
+/// Loads a document written by [`save_state`].
+pub fn load_state(input: String) -> Result<State, SolverError> {
+    parse_saved(input)?.into_state()
+}
+
+/// Parses a document after checking its version.
+pub(crate) fn parse_saved(input: String) -> Result<SavedState, SolverError> {
+    let version: Version = serde_json::from_str(&input)?;
+    if !SUPPORTED_VERSIONS.contains(&version.version) {
+        return Err(SolverError::UnsupportedVersion(version.version));
+    }
+    Ok(serde_json::from_str(&input)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mcts::GameState;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    #[test]
+    fn round_trip() {
+        let tiles = crate::parse_file("../resources/tiles.json").unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut state = State::with_tiles(tiles);
+        for _ in 0..7 {
+            let m = *state.available_moves().choose(&mut rng).unwrap();
+            state.make_move(&m);
+        }
+        assert!(state.count_solved_tasks() > 0);
+
+        let json = save_state(&state).unwrap();
+        let loaded = load_state(json.clone()).unwrap();
+        assert_eq!(state.board_tiles(), loaded.board_tiles());
+        assert_eq!(
+            state.remaining_tiles().collect::<Vec<_>>(),
+            loaded.remaining_tiles().collect::<Vec<_>>()
+        );
+        assert_eq!(json, save_state(&loaded).unwrap());
+        assert_eq!(
+            crate::game_state_as_json(&state).unwrap(),
+            crate::game_state_as_json(&loaded).unwrap()
+        );
+    }
+
+    #[test]
+    fn keeps_budget() {
+        let tiles = crate::parse_file("../resources/tiles.json").unwrap();
+        let mut state = State::with_budget(tiles, TileBudget::Cost(10));
+        let m = state.available_moves()[0];
+        state.make_move(&m);
+
+        let json = save_state(&state).unwrap();
+        assert!(json.ends_with(r#","budget":{"cost":10}}"#));
+        let loaded = load_state(json).unwrap();
+        assert_eq!(Some(TileBudget::Cost(10)), loaded.budget());
+        assert_eq!(state.max_cost(), loaded.max_cost());
+        assert_eq!(state.available_moves(), loaded.available_moves());
+    }
+
+    #[test]
+    fn recomputes_solved_flags() {
+        let json = r#"{"version": 1, "board": [
+                {"position": [0, 0], "tile": {"color": "Red", "cost": 3,
+                    "tasks": [{"colors": ["Blue"], "solved": true}]}},
+                {"position": [1, 0], "tile": {"color": "Teal", "cost": 2,
+                    "tasks": [{"colors": ["Red"], "solved": false}]}}
+            ], "remaining": []}"#;
+        let state = load_state(json.to_string()).unwrap();
+        let solved: Vec<u8> = state
+            .board_tiles()
+            .iter()
+            .map(|(_, t)| t.solved_flags())
+            .collect();
+        assert_eq!(vec![0, 1], solved);
+    }
+
+    #[test]
+    fn versions() {
+        let state = State::with_tiles(crate::parse_file("../resources/tiles.json").unwrap());
+        let json = save_state(&state).unwrap();
+        assert!(json.starts_with(r#"{"version":2,"#));
+        assert!(load_state(json.replace(r#""version":2"#, r#""version":1"#)).is_ok());
+        assert!(matches!(
+            load_state(json.replace(r#""version":2"#, r#""version":3"#)),
+            Err(SolverError::UnsupportedVersion(3))
+        ));
+        assert!(matches!(
+            load_state(r#"{"board": [], "remaining": []}"#.to_string()),
+            Err(SolverError::Schema(_))
+        ));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::nova_luna::State;
+use std::fmt;
+use std::str::FromStr;
+
+/// Fixed-point factor of the evaluations, as the search only accumulates integer rewards.
+pub(crate) const SCORE_SCALE: f64 = 1000.0;
+
+/// Measure of how good a board is.
+///
+/// Every solved task places one of the player's tokens and the first player placing all of
+/// their tokens wins, while every tile costs steps on the moon track.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Scoring {
+    /// Number of tokens placed.
+    #[default]
+    Tokens,
+    /// Number of tokens placed per step on the moon track spent for the tiles.
+    TokensPerCost,
+    /// Weighted sum of the number of tokens and the number of tokens per step.
+    Weighted { tokens: f64, tokens_per_cost: f64 },
+}
+
+impl Scoring {
+    pub fn score(&self, state: &State) -> f64 {
+        self.score_of(state.count_solved_tasks(), state.spent_cost())
+    }
+
+    /// Scores a board with the number of solved tasks and the spent cost.
+    pub(crate) fn score_of(&self, solved_tasks: usize, cost: usize) -> f64 {
+        let tokens = solved_tasks as f64;
+        let tokens_per_cost = match cost {
+            0 => 0.0,
+            cost => tokens / cost as f64,
+        };
+        match *self {
+            Scoring::Tokens => tokens,
+            Scoring::TokensPerCost => tokens_per_cost,
+            Scoring::Weighted {
+                tokens: w_tokens,
+                tokens_per_cost: w_tokens_per_cost,
+            } => w_tokens * tokens + w_tokens_per_cost * tokens_per_cost,
+        }
+    }
+}
+
+/// Returns the score as reward of the search.
+pub(crate) fn reward(score: f64) -> i64 {
+    (score * SCORE_SCALE).round() as i64
+}
+
+impl FromStr for Scoring {
+    type Err = String;
+
+    /// Parses "tokens", "tokens-per-cost" or "weighted:<tokens>:<tokens-per-cost>".
+    fn from_str(s: &str) -> Result<Scoring, String> {
+        match s {
+            "tokens" => Ok(Scoring::Tokens),
+            "tokens-per-cost" => Ok(Scoring::TokensPerCost),
+            _ => {
+                let weights: Vec<&str> = s
+                    .strip_prefix("weighted:")
+                    .map(|w| w.split(':').collect())
+                    .unwrap_or_default();
+                match weights[..] {
+                    [tokens, tokens_per_cost] => {
+                        match (tokens.parse::<f64>(), tokens_per_cost.parse::<f64>()) {
+                            // NaN and infinite weights would break the comparison of scores.
+                            (Ok(tokens), Ok(tokens_per_cost))
+                                if tokens.is_finite() && tokens_per_cost.is_finite() =>
+                            {
+                                Ok(Scoring::Weighted {
+                                    tokens,
+                                    tokens_per_cost,
+                                })
+                            }
+                            _ => Err(format!("invalid weights in \"{}\"", s)),
+                        }
+                    }
+                    _ => Err(format!("unknown scoring \"{}\"", s)),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Scoring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scoring::Tokens => write!(f, "tokens"),
+            Scoring::TokensPerCost => write!(f, "tokens-per-cost"),
+            Scoring::Weighted {
+                tokens,
+                tokens_per_cost,
+            } => write!(f, "weighted:{}:{}", tokens, tokens_per_cost),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nova_luna::{Color, Position, Task, Tile};
+    use mcts::GameState;
+
+    #[test]
+    fn scores() {
+        let tile1 = Tile::new(2, Color::Teal, vec![]);
+        let tile2 = Tile::new(6, Color::Blue, vec![Task::new(vec![Color::Teal])]);
+        let mut state = State::with_tiles(vec![tile1.clone(), tile2.clone()]);
+        assert_eq!(0.0, Scoring::TokensPerCost.score(&state));
+
+        for (tile, x) in [(&tile1, 0), (&tile2, 1)] {
+            let id = state.tile_id(tile).unwrap();
+            state.make_move(&crate::nova_luna::Move::new(id, Position::new(x, 0)));
+        }
+
+        assert_eq!(1.0, Scoring::Tokens.score(&state));
+        assert_eq!(0.125, Scoring::TokensPerCost.score(&state));
+        let weighted = Scoring::Weighted {
+            tokens: 2.0,
+            tokens_per_cost: 8.0,
+        };
+        assert_eq!(3.0, weighted.score(&state));
+        assert_eq!(3000, reward(weighted.score(&state)));
+    }
+
+    #[test]
+    fn parse() {
+        for scoring in [
+            Scoring::Tokens,
+            Scoring::TokensPerCost,
+            Scoring::Weighted {
+                tokens: 1.0,
+                tokens_per_cost: 10.5,
+            },
+        ] {
+            assert_eq!(Ok(scoring), scoring.to_string().parse());
+        }
+        assert!("weighted:1".parse::<Scoring>().is_err());
+        assert!("weighted:a:1".parse::<Scoring>().is_err());
+        assert!("weighted:NaN:1".parse::<Scoring>().is_err());
+        assert!("weighted:1:inf".parse::<Scoring>().is_err());
+        assert!("cost".parse::<Scoring>().is_err());
+    }
+}
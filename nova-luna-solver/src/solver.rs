@@ -1,26 +1,92 @@
-use crate::nova_luna::{Move, State};
-use mcts::transposition_table::ApproxTable;
-use mcts::tree_policy::UCTPolicy;
-use mcts::{CycleBehaviour, Evaluator, SearchHandle, MCTS};
+use crate::evaluator::LeafEvaluator;
+use crate::nova_luna::{BoardKey, Move, State};
+use crate::scoring::{reward, Scoring, SCORE_SCALE};
+use crate::Transpositions;
+use mcts::transposition_table::{TranspositionHash, TranspositionTable};
+use mcts::tree_policy::TreePolicy;
+use mcts::{CycleBehaviour, Evaluator, GameState, MoveInfo, SearchHandle, SearchNode, MCTS};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
-pub struct StateEvaluator;
+/// State of the search tree, whose moves place the tiles relative to the
+/// [lowest corner](State::corner) of the board.
+///
+/// The transposition table finds shifted copies of a board by their placed tiles relative to the
+/// corner, and the search continues every copy with the moves of the copy stored first.
+/// Relative to the corner, these moves place the same tiles next to the same tiles on every copy.
+#[derive(Clone)]
+pub struct SearchState(pub State);
+
+impl SearchState {
+    /// Turns a move of the search into the move on the board of the state.
+    pub fn absolute(&self, mov: &Move) -> Move {
+        mov.shifted_by(self.0.corner())
+    }
+}
+
+impl GameState for SearchState {
+    type Move = Move;
+    type Player = ();
+    type MoveList = Vec<Move>;
+
+    fn current_player(&self) -> Self::Player {}
+
+    fn available_moves(&self) -> Vec<Move> {
+        let corner = self.0.corner();
+        self.0
+            .available_moves()
+            .iter()
+            .map(|m| m.relative_to(corner))
+            .collect()
+    }
+
+    fn make_move(&mut self, mov: &Move) {
+        let mov = self.absolute(mov);
+        self.0.make_move(&mov);
+    }
+}
+
+/// Evaluates new nodes with the leaf evaluator, converting the score into the integer rewards
+/// of the search.
+pub struct StateEvaluator {
+    pub evaluator: Arc<dyn LeafEvaluator>,
+    pub scoring: Scoring,
+    /// Seeds of the random number generators of the evaluator in the search threads.
+    pub seeds: ThreadSeeds,
+}
 
 impl Evaluator<NovaLunaBoardGameMCTS> for StateEvaluator {
     type StateEvaluation = i64;
 
     fn evaluate_new_state(
         &self,
-        state: &State,
+        state: &SearchState,
         moves: &Vec<Move>,
-        _: Option<SearchHandle<NovaLunaBoardGameMCTS>>,
+        handle: Option<SearchHandle<NovaLunaBoardGameMCTS>>,
     ) -> (Vec<()>, i64) {
         let player = (0..moves.len()).map(|_| ()).collect();
-        (player, state.count_solved_tasks() as i64)
+        let score = match handle {
+            Some(mut handle) => {
+                let rng = handle.thread_data().extra_data.get(&self.seeds);
+                self.evaluator.evaluate(&state.0, &self.scoring, rng)
+            }
+            // The root is evaluated outside of the search threads.
+            None => {
+                let mut rng = StdRng::seed_from_u64(self.seeds.seed);
+                self.evaluator.evaluate(&state.0, &self.scoring, &mut rng)
+            }
+        };
+        (player, reward(score))
     }
 
     fn evaluate_existing_state(
         &self,
-        _: &State,
+        _: &SearchState,
         evaln: &i64,
         _: SearchHandle<NovaLunaBoardGameMCTS>,
     ) -> i64 {
@@ -36,14 +102,413 @@ impl Evaluator<NovaLunaBoardGameMCTS> for StateEvaluator {
 pub struct NovaLunaBoardGameMCTS;
 
 impl MCTS for NovaLunaBoardGameMCTS {
-    type State = State;
+    type State = SearchState;
     type Eval = StateEvaluator;
-    type TreePolicy = UCTPolicy;
+    type TreePolicy = SeededUCTPolicy;
     type NodeData = ();
-    type TranspositionTable = ApproxTable<Self>;
-    type ExtraThreadData = ();
+    type TranspositionTable = SearchTable;
+    type ExtraThreadData = ThreadRng;
 
     fn cycle_behaviour(&self) -> CycleBehaviour<Self> {
         CycleBehaviour::UseCurrentEvalWhenCycleDetected
     }
 }
+
+/// Use of the transposition table during a search.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct TableStatistics {
+    /// Number of new nodes the search looked up.
+    pub lookups: u64,
+    /// Lookups which found the node of the same board.
+    pub hits: u64,
+    /// Lookups which found the node of another board with the same hash, which are treated as
+    /// misses.
+    pub collisions: u64,
+    pub hit_rate: f64,
+}
+
+impl TableStatistics {
+    /// Adds up the statistics of two searches.
+    pub fn combine(self, other: TableStatistics) -> TableStatistics {
+        TableStatistics::new(
+            self.lookups + other.lookups,
+            self.hits + other.hits,
+            self.collisions + other.collisions,
+        )
+    }
+
+    fn new(lookups: u64, hits: u64, collisions: u64) -> TableStatistics {
+        TableStatistics {
+            lookups,
+            hits,
+            collisions,
+            hit_rate: if lookups == 0 {
+                0.0
+            } else {
+                hits as f64 / lookups as f64
+            },
+        }
+    }
+}
+
+/// Counters of a [`SearchTable`], shared with the search owning the table.
+#[derive(Default)]
+pub struct TableCounters {
+    lookups: AtomicU64,
+    hits: AtomicU64,
+    collisions: AtomicU64,
+}
+
+impl TableCounters {
+    pub fn statistics(&self) -> TableStatistics {
+        TableStatistics::new(
+            self.lookups.load(Ordering::Relaxed),
+            self.hits.load(Ordering::Relaxed),
+            self.collisions.load(Ordering::Relaxed),
+        )
+    }
+}
+
+type Node = SearchNode<NovaLunaBoardGameMCTS>;
+
+/// Node of the search tree stored in a table.
+///
+/// The search tree owns both its nodes and the table, and frees the nodes only when it is
+/// dropped along with the table, as the search never moves the root of the tree. So every node
+/// outlives the entries referencing it.
+struct NodeRef(*const Node);
+
+impl NodeRef {
+    /// Returns the node for as long as the table holding the entry lives.
+    fn get<'a>(&self) -> &'a Node {
+        // SAFETY: the node outlives the table, see above.
+        unsafe { &*self.0 }
+    }
+}
+
+// SAFETY: the entries only hand out shared references to the nodes, which the search threads
+// share for all nodes of the tree anyway.
+unsafe impl Send for NodeRef {}
+
+/// Entry of an [`ApproxTable`].
+struct Slot {
+    hash: u64,
+    key: BoardKey,
+    node: NodeRef,
+}
+
+/// Hash table with a fixed number of entries, which finds the entry of a board by linear probing
+/// from its hash. Boards with the same hash are told apart by their
+/// [placed tiles](State::shifted_key), and only the board stored first shares its node. Like the
+/// table of the `mcts` crate, a table filled by two thirds stores no further boards.
+struct ApproxTable {
+    hash: fn(&State) -> u64,
+    slots: Box<[Mutex<Option<Slot>>]>,
+    len: AtomicUsize,
+}
+
+/// Result of probing an [`ApproxTable`] for a board.
+enum Probe<'a> {
+    Found(&'a Node),
+    /// The board is not stored, but another board with the same hash is.
+    Collision,
+    Missing,
+}
+
+impl ApproxTable {
+    fn new(entries: usize, hash: fn(&State) -> u64) -> ApproxTable {
+        ApproxTable {
+            hash,
+            slots: (0..entries.max(1).next_power_of_two())
+                .map(|_| Mutex::default())
+                .collect(),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Probes the entries from the hash of the state until one is empty or holds the board, and
+    /// stores the node in the empty entry unless the table is full.
+    fn probe(&self, state: &State, insert: Option<&Node>) -> Probe<'_> {
+        let hash = (self.hash)(state);
+        let mut key = None;
+        let mut collision = false;
+        let mask = self.slots.len() - 1;
+        for i in 0..self.slots.len() {
+            let mut slot = self.slots[(hash as usize).wrapping_add(i) & mask]
+                .lock()
+                .expect("table is not poisoned");
+            match &*slot {
+                Some(entry) if entry.hash == hash => {
+                    if entry.key == *key.get_or_insert_with(|| state.shifted_key()) {
+                        return Probe::Found(entry.node.get());
+                    }
+                    collision = true;
+                }
+                Some(_) => {}
+                None => {
+                    let full = self.len.load(Ordering::Relaxed) * 3 >= self.slots.len() * 2;
+                    if let Some(node) = insert.filter(|_| !full) {
+                        self.len.fetch_add(1, Ordering::Relaxed);
+                        *slot = Some(Slot {
+                            hash,
+                            key: key.unwrap_or_else(|| state.shifted_key()),
+                            node: NodeRef(node),
+                        });
+                    }
+                    break;
+                }
+            }
+        }
+        match collision {
+            true => Probe::Collision,
+            false => Probe::Missing,
+        }
+    }
+}
+
+enum Table {
+    Off,
+    Approx(ApproxTable),
+    Exact(Mutex<HashMap<BoardKey, NodeRef>>),
+}
+
+/// Transposition table of the search chosen by [`Transpositions`], which counts its hits and
+/// collisions.
+pub struct SearchTable {
+    table: Table,
+    counters: Arc<TableCounters>,
+}
+
+impl SearchTable {
+    /// Creates the table, sizing an approximate table without a given size for the number of
+    /// nodes.
+    pub fn new(transpositions: Transpositions, num_nodes: usize) -> SearchTable {
+        SearchTable::with_hash(transpositions, num_nodes, TranspositionHash::hash)
+    }
+
+    /// Creates the table like [`SearchTable::new`], hashing the boards with the function.
+    pub fn with_hash(
+        transpositions: Transpositions,
+        num_nodes: usize,
+        hash: fn(&State) -> u64,
+    ) -> SearchTable {
+        let table = match transpositions {
+            Transpositions::Off => Table::Off,
+            Transpositions::Approx(None) => {
+                Table::Approx(ApproxTable::new(num_nodes * 3 / 2, hash))
+            }
+            Transpositions::Approx(Some(entries)) => Table::Approx(ApproxTable::new(entries, hash)),
+            Transpositions::Exact => Table::Exact(Mutex::default()),
+        };
+        SearchTable {
+            table,
+            counters: Arc::default(),
+        }
+    }
+
+    pub fn counters(&self) -> Arc<TableCounters> {
+        self.counters.clone()
+    }
+}
+
+// SAFETY: `insert` only returns `None` when it stores the node or leaves the table unchanged.
+unsafe impl TranspositionTable<NovaLunaBoardGameMCTS> for SearchTable {
+    fn insert<'a>(
+        &'a self,
+        key: &SearchState,
+        value: &'a Node,
+        _: SearchHandle<NovaLunaBoardGameMCTS>,
+    ) -> Option<&'a Node> {
+        match &self.table {
+            Table::Off => None,
+            // Only lookups are counted, inserting follows a lookup which missed.
+            Table::Approx(table) => match table.probe(&key.0, Some(value)) {
+                Probe::Found(node) => Some(node),
+                Probe::Collision | Probe::Missing => None,
+            },
+            Table::Exact(map) => {
+                match map
+                    .lock()
+                    .expect("table is not poisoned")
+                    .entry(key.0.shifted_key())
+                {
+                    Entry::Occupied(entry) => Some(entry.get().get()),
+                    Entry::Vacant(entry) => {
+                        entry.insert(NodeRef(value));
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    fn lookup<'a>(
+        &'a self,
+        key: &SearchState,
+        _: SearchHandle<NovaLunaBoardGameMCTS>,
+    ) -> Option<&'a Node> {
+        if !matches!(self.table, Table::Off) {
+            self.counters.lookups.fetch_add(1, Ordering::Relaxed);
+        }
+        let node = match &self.table {
+            Table::Off => None,
+            Table::Approx(table) => match table.probe(&key.0, None) {
+                Probe::Found(node) => Some(node),
+                Probe::Collision => {
+                    self.counters.collisions.fetch_add(1, Ordering::Relaxed);
+                    None
+                }
+                Probe::Missing => None,
+            },
+            Table::Exact(map) => map
+                .lock()
+                .expect("table is not poisoned")
+                .get(&key.0.shifted_key())
+                .map(NodeRef::get),
+        }?;
+        self.counters.hits.fetch_add(1, Ordering::Relaxed);
+        Some(node)
+    }
+}
+
+/// UCT policy of the `mcts` crate, breaking ties between equally rated moves with a seeded
+/// random number generator instead of a fixed one.
+pub struct SeededUCTPolicy {
+    exploration_constant: f64,
+    seeds: ThreadSeeds,
+}
+
+impl SeededUCTPolicy {
+    pub fn new(exploration_constant: f64, seed: u64) -> Self {
+        assert!(
+            exploration_constant > 0.0,
+            "exploration constant is {} (must be positive)",
+            exploration_constant
+        );
+        SeededUCTPolicy {
+            exploration_constant,
+            seeds: ThreadSeeds::new(seed),
+        }
+    }
+}
+
+/// Seeds of the random number generators of the search threads. Every thread adds the number of
+/// threads started before it to the seed, so that no two threads draw the same numbers.
+pub struct ThreadSeeds {
+    seed: u64,
+    started: AtomicU64,
+}
+
+impl ThreadSeeds {
+    pub fn new(seed: u64) -> ThreadSeeds {
+        ThreadSeeds {
+            seed,
+            started: AtomicU64::new(0),
+        }
+    }
+
+    fn next(&self) -> u64 {
+        self.seed
+            .wrapping_add(self.started.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Random number generator of a search thread, seeded on first use.
+#[derive(Default)]
+pub struct ThreadRng(Option<StdRng>);
+
+impl ThreadRng {
+    pub fn get(&mut self, seeds: &ThreadSeeds) -> &mut StdRng {
+        self.0
+            .get_or_insert_with(|| StdRng::seed_from_u64(seeds.next()))
+    }
+}
+
+impl<Spec: MCTS<TreePolicy = Self>> TreePolicy<Spec> for SeededUCTPolicy {
+    type MoveEvaluation = ();
+    type ThreadLocalData = ThreadRng;
+
+    fn choose_child<'a, MoveIter>(
+        &self,
+        moves: MoveIter,
+        mut handle: SearchHandle<Spec>,
+    ) -> &'a MoveInfo<Spec>
+    where
+        MoveIter: Iterator<Item = &'a MoveInfo<Spec>> + Clone,
+    {
+        let total_visits = moves.clone().map(|x| x.visits()).sum::<u64>();
+        let ln_adjusted_total = ((total_visits + 1) as f64).ln();
+        let rng = handle.thread_data().policy_data.get(&self.seeds);
+
+        let mut choice = None;
+        let mut num_optimal = 0;
+        let mut best_so_far = f64::NEG_INFINITY;
+        for mov in moves {
+            // http://mcts.ai/pubs/mcts-survey-master.pdf
+            let score = if mov.visits() == 0 {
+                f64::INFINITY
+            } else {
+                let explore_term = 2.0 * (ln_adjusted_total / mov.visits() as f64).sqrt();
+                let mean_action_value =
+                    mov.sum_rewards() as f64 / mov.visits() as f64 / SCORE_SCALE;
+                self.exploration_constant * explore_term + mean_action_value
+            };
+            if score > best_so_far {
+                choice = Some(mov);
+                num_optimal = 1;
+                best_so_far = score;
+            } else if score == best_so_far {
+                num_optimal += 1;
+                if rng.gen_range(0..num_optimal) == 0 {
+                    choice = Some(mov);
+                }
+            }
+        }
+        choice.expect("search must only choose between available moves")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::SolvedTasks;
+    use mcts::MCTSManager;
+
+    #[test]
+    fn counts_collisions_once() {
+        let tiles = crate::parse_file("../resources/tiles.json").unwrap();
+        // All boards collide.
+        let table = SearchTable::with_hash(Transpositions::Approx(Some(4)), 0, |_| 1);
+        let counters = table.counters();
+        let mut mcts = MCTSManager::new(
+            SearchState(State::with_tiles(tiles[..4].to_vec())),
+            NovaLunaBoardGameMCTS,
+            StateEvaluator {
+                evaluator: Arc::new(SolvedTasks),
+                scoring: Scoring::Tokens,
+                seeds: ThreadSeeds::new(1),
+            },
+            SeededUCTPolicy::new(2.0, 0),
+            table,
+        );
+        mcts.playout_n(200);
+
+        let statistics = counters.statistics();
+        assert!(statistics.collisions > 0);
+        assert!(statistics.hits + statistics.collisions <= statistics.lookups);
+    }
+
+    #[test]
+    fn threads_draw_different_numbers() {
+        let seeds = ThreadSeeds::new(42);
+        let mut first = ThreadRng::default();
+        let mut second = ThreadRng::default();
+        let a: u64 = first.get(&seeds).gen();
+        let b: u64 = second.get(&seeds).gen();
+        assert_ne!(a, b);
+
+        let mut single = ThreadRng::default();
+        let c: u64 = single.get(&ThreadSeeds::new(42)).gen();
+        assert_eq!(a, c);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::error::SolverError;
+use crate::nova_luna::{Tile, MAX_TASKS, MAX_TILES};
+use serde::Serialize;
+use std::fmt;
+
+/// A constraint of the printed Nova Luna tiles which is violated by an input tile.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum Problem {
+    CostOutOfRange(usize),
+    TooFewTasks(usize),
+    TooManyTasks(usize),
+    EmptyTask,
+    TooManyColors(usize),
+}
+
+/// A problem found by [`validate_tiles`], located by the index of the tile and a JSON path.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub tile: usize,
+    pub task: Option<usize>,
+    pub path: String,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+        match &self.problem {
+            Problem::CostOutOfRange(cost) => {
+                write!(f, "cost {} is outside of {:?}", cost, Tile::COST_RANGE)
+            }
+            Problem::TooFewTasks(n) => write!(
+                f,
+                "{} tasks are less than the minimum of {}",
+                n,
+                Tile::TASKS_RANGE.start()
+            ),
+            Problem::TooManyTasks(n) => write!(
+                f,
+                "{} tasks exceed the maximum of {}",
+                n,
+                Tile::TASKS_RANGE.end()
+            ),
+            Problem::EmptyTask => write!(f, "task does not require any color"),
+            Problem::TooManyColors(n) => write!(
+                f,
+                "{} colors exceed the maximum of {}",
+                n,
+                Tile::TASK_COLORS_RANGE.end()
+            ),
+        }
+    }
+}
+
+/// Checks every tile against the constraints of the printed tiles and reports all problems.
+pub fn validate_tiles(tiles: &[Tile]) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    for (idx, tile) in tiles.iter().enumerate() {
+        let mut report = |task: Option<usize>, path: String, problem: Problem| {
+            diagnostics.push(Diagnostic {
+                tile: idx,
+                task,
+                path,
+                problem,
+            })
+        };
+
+        if !Tile::COST_RANGE.contains(&tile.cost()) {
+            report(
+                None,
+                format!("$[{}].cost", idx),
+                Problem::CostOutOfRange(tile.cost()),
+            );
+        }
+
+        let num_tasks = tile.tasks().len();
+        let path = format!("$[{}].tasks", idx);
+        if num_tasks < *Tile::TASKS_RANGE.start() {
+            report(None, path, Problem::TooFewTasks(num_tasks));
+        } else if num_tasks > *Tile::TASKS_RANGE.end() {
+            report(None, path, Problem::TooManyTasks(num_tasks));
+        }
+
+        for (task_idx, task) in tile.tasks().iter().enumerate() {
+            let num_colors = task.colors().len();
+            let path = format!("$[{}].tasks[{}]", idx, task_idx);
+            if num_colors == 0 {
+                report(Some(task_idx), path, Problem::EmptyTask);
+            } else if num_colors > *Tile::TASK_COLORS_RANGE.end() {
+                report(Some(task_idx), path, Problem::TooManyColors(num_colors));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Rejects tiles with values which can be deserialized but can never be handled by the solver.
+pub(crate) fn check_schema(tiles: &[Tile]) -> Result<(), SolverError> {
+    if tiles.len() > MAX_TILES {
+        return Err(SolverError::TooManyTiles(tiles.len()));
+    }
+    for diagnostic in validate_tiles(tiles) {
+        let tile = diagnostic.tile;
+        match (diagnostic.problem, diagnostic.task) {
+            (Problem::CostOutOfRange(cost), _) => {
+                return Err(SolverError::CostOutOfRange { tile, cost })
+            }
+            (Problem::TooManyTasks(tasks), _) if tasks > MAX_TASKS => {
+                return Err(SolverError::TooManyTasks { tile, tasks })
+            }
+            (Problem::EmptyTask, Some(task)) => return Err(SolverError::EmptyTask { tile, task }),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nova_luna::{Color, Task};
+
+    #[test]
+    fn valid_tiles() {
+        let tiles = crate::parse_file("../resources/tiles.json").unwrap();
+        assert!(validate_tiles(&tiles).is_empty());
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let tiles = vec![
+            Tile::new(1, Color::Teal, vec![Task::new(vec![Color::Red])]),
+            Tile::new(9, Color::Teal, vec![]),
+            Tile::new(
+                2,
+                Color::Blue,
+                vec![
+                    Task::new(vec![Color::Red]),
+                    Task::new(vec![]),
+                    Task::new(vec![Color::Red; 5]),
+                    Task::new(vec![Color::Teal]),
+                ],
+            ),
+        ];
+
+        let diagnostics = validate_tiles(&tiles);
+
+        let problems: Vec<(usize, &str, &Problem)> = diagnostics
+            .iter()
+            .map(|d| (d.tile, d.path.as_str(), &d.problem))
+            .collect();
+        assert_eq!(
+            vec![
+                (1, "$[1].cost", &Problem::CostOutOfRange(9)),
+                (1, "$[1].tasks", &Problem::TooFewTasks(0)),
+                (2, "$[2].tasks", &Problem::TooManyTasks(4)),
+                (2, "$[2].tasks[1]", &Problem::EmptyTask),
+                (2, "$[2].tasks[2]", &Problem::TooManyColors(5)),
+            ],
+            problems
+        );
+        assert_eq!(
+            "$[1].cost: cost 9 is outside of 1..=7",
+            diagnostics[0].to_string()
+        );
+    }
+
+    #[test]
+    fn schema_violations() {
+        let tile = Tile::new(1, Color::Teal, vec![Task::new(vec![Color::Red])]);
+        assert!(check_schema(&[tile]).is_ok());
+
+        let tile = Tile::new(8, Color::Teal, vec![]);
+        assert!(matches!(
+            check_schema(&[tile]),
+            Err(SolverError::CostOutOfRange { tile: 0, cost: 8 })
+        ));
+
+        let tile = Tile::new(3, Color::Teal, vec![Task::new(vec![Color::Red])]);
+        let empty = Tile::new(
+            3,
+            Color::Teal,
+            vec![Task::new(vec![Color::Red]), Task::new(vec![])],
+        );
+        assert!(matches!(
+            check_schema(&[tile, empty]),
+            Err(SolverError::EmptyTask { tile: 1, task: 1 })
+        ));
+
+        let tile = Tile::new(3, Color::Teal, vec![Task::new(vec![Color::Red]); 4]);
+        assert!(check_schema(&[tile]).is_ok());
+        let tile = Tile::new(3, Color::Teal, vec![Task::new(vec![Color::Red]); 9]);
+        assert!(matches!(
+            check_schema(&[tile]),
+            Err(SolverError::TooManyTasks { tile: 0, tasks: 9 })
+        ));
+
+        let tile = Tile::new(3, Color::Teal, vec![Task::new(vec![Color::Red])]);
+        assert!(matches!(
+            check_schema(&vec![tile; 129]),
+            Err(SolverError::TooManyTiles(129))
+        ));
+    }
+}
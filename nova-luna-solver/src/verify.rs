@@ -0,0 +1,228 @@
+//! Consistency check of a saved or plain board, whose solved flags and layout may have been edited
+//! by hand or produced by another program.
+
+use crate::error::SolverError;
+use crate::nova_luna::{Position, Tile};
+use crate::saved::{parse_saved, SavedTile};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// A way in which a board differs from a board the rules could produce.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum Inconsistency {
+    /// The position is already taken by the tile with the index.
+    DuplicatePosition(usize),
+    /// The tile is not connected to the first tile of the board.
+    Disconnected,
+    /// The stored solved flag of a task differs from the recomputed one.
+    SolvedFlag { stored: bool, recomputed: bool },
+}
+
+/// An inconsistency found by [`verify_board`], located by the index of the tile and a JSON path.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Discrepancy {
+    pub tile: usize,
+    pub task: Option<usize>,
+    pub path: String,
+    pub problem: Inconsistency,
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+        match &self.problem {
+            Inconsistency::DuplicatePosition(other) => {
+                write!(f, "position is already taken by tile {}", other)
+            }
+            Inconsistency::Disconnected => write!(f, "tile is not connected to the board"),
+            Inconsistency::SolvedFlag { stored, recomputed } => write!(
+                f,
+                "task is stored as {} but is {}",
+                solved_str(*stored),
+                solved_str(*recomputed)
+            ),
+        }
+    }
+}
+
+fn solved_str(solved: bool) -> &'static str {
+    if solved {
+        "solved"
+    } else {
+        "unsolved"
+    }
+}
+
+/// Checks the layout of the board and recomputes every task by searching the color groups next to
+/// its tile, reporting all differences to the stored solved flags.
+pub fn verify_board(board: &[(Position, Tile)]) -> Vec<Discrepancy> {
+    verify_tiles(board, "$.board")
+}
+
+/// Verifies the board like [`verify_board`], with the JSON paths of the tiles starting at `root`.
+fn verify_tiles(board: &[(Position, Tile)], root: &str) -> Vec<Discrepancy> {
+    let mut discrepancies = vec![];
+    let mut tiles: HashMap<Position, Tile> = HashMap::new();
+    let mut indices: HashMap<Position, usize> = HashMap::new();
+    for (idx, (position, tile)) in board.iter().enumerate() {
+        match indices.get(position) {
+            Some(other) => discrepancies.push(Discrepancy {
+                tile: idx,
+                task: None,
+                path: format!("{}[{}].position", root, idx),
+                problem: Inconsistency::DuplicatePosition(*other),
+            }),
+            None => {
+                indices.insert(*position, idx);
+                tiles.insert(*position, tile.clone());
+            }
+        }
+    }
+
+    let mut connected = HashSet::new();
+    let mut unvisited: Vec<Position> = board.first().map(|(p, _)| *p).into_iter().collect();
+    while let Some(position) = unvisited.pop() {
+        if tiles.contains_key(&position) && connected.insert(position) {
+            unvisited.extend(position.adjacent());
+        }
+    }
+
+    for (idx, (position, tile)) in board.iter().enumerate() {
+        if indices[position] != idx {
+            continue;
+        }
+        if !connected.contains(position) {
+            discrepancies.push(Discrepancy {
+                tile: idx,
+                task: None,
+                path: format!("{}[{}].position", root, idx),
+                problem: Inconsistency::Disconnected,
+            });
+        }
+        for (task_idx, task) in tile.tasks().iter().enumerate() {
+            let stored = tile.solved_flags() & (1 << task_idx) != 0;
+            let recomputed = task.is_solved(*position, &tiles);
+            if stored != recomputed {
+                discrepancies.push(Discrepancy {
+                    tile: idx,
+                    task: Some(task_idx),
+                    path: format!("{}[{}].tile.tasks[{}].solved", root, idx, task_idx),
+                    problem: Inconsistency::SolvedFlag { stored, recomputed },
+                });
+            }
+        }
+    }
+    discrepancies.sort_by_key(|d| (d.tile, d.task));
+    discrepancies
+}
+
+/// Verifies the board of a document written by [`save_state`](crate::save_state), or of a plain
+/// board, a list of tiles with their positions like the output of
+/// [`game_state_as_json`](crate::game_state_as_json).
+pub fn verify_string(input: String) -> Result<Vec<Discrepancy>, SolverError> {
+    let (tiles, root): (Vec<SavedTile>, _) = if input.trim_start().starts_with('[') {
+        (serde_json::from_str(&input)?, "$")
+    } else {
+        (parse_saved(input)?.board, "$.board")
+    };
+    let board: Vec<(Position, Tile)> = tiles.into_iter().map(|t| (t.position, t.tile)).collect();
+    Ok(verify_tiles(&board, root))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nova_luna::{Color, State, Task};
+    use crate::save_state;
+    use mcts::GameState;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    fn problems(board: &[(Position, Tile)]) -> Vec<(usize, Option<usize>, Inconsistency)> {
+        verify_board(board)
+            .into_iter()
+            .map(|d| (d.tile, d.task, d.problem))
+            .collect()
+    }
+
+    #[test]
+    fn saved_boards_are_consistent() {
+        let tiles = crate::parse_file("../resources/tiles.json").unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut state = State::with_tiles(tiles);
+        while let Some(m) = state.available_moves().choose(&mut rng).copied() {
+            state.make_move(&m);
+        }
+        assert!(state.count_solved_tasks() > 0);
+        assert!(verify_string(save_state(&state).unwrap())
+            .unwrap()
+            .is_empty());
+        assert!(verify_string(crate::game_state_as_json(&state).unwrap())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn verifies_plain_boards() {
+        let input = r#"[
+            {"position": [0, 0], "tile": {"color": "Red", "cost": 3,
+                "tasks": [{"colors": ["Blue"], "solved": true}]}},
+            {"position": [2, 0], "tile": {"color": "Blue", "cost": 2, "tasks": [["Blue"]]}}
+        ]"#;
+        let messages: Vec<String> = verify_string(input.to_string())
+            .unwrap()
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "$[0].tile.tasks[0].solved: task is stored as solved but is unsolved",
+                "$[1].position: tile is not connected to the board",
+            ],
+            messages
+        );
+    }
+
+    #[test]
+    fn reports_every_inconsistency() {
+        let task = Task::new(vec![Color::Blue]);
+        let red = Tile::new(3, Color::Red, vec![task.clone()]);
+        let blue = Tile::new(2, Color::Blue, vec![task]);
+        let board = vec![
+            (Position::new(0, 0), red.clone()),
+            (Position::new(1, 0), blue.with_solved_flags(1)),
+            (Position::new(1, 0), red.clone()),
+            (Position::new(3, 0), red),
+        ];
+
+        assert_eq!(
+            vec![
+                (
+                    0,
+                    Some(0),
+                    Inconsistency::SolvedFlag {
+                        stored: false,
+                        recomputed: true
+                    }
+                ),
+                (
+                    1,
+                    Some(0),
+                    Inconsistency::SolvedFlag {
+                        stored: true,
+                        recomputed: false
+                    }
+                ),
+                (2, None, Inconsistency::DuplicatePosition(1)),
+                (3, None, Inconsistency::Disconnected),
+            ],
+            problems(&board)
+        );
+        assert_eq!(
+            "$.board[1].tile.tasks[0].solved: task is stored as solved but is unsolved",
+            verify_board(&board)[1].to_string()
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Keys of the translation invariant hash of a board.
+//!
+//! Like a Zobrist hash, the hash of a board combines a key of every placed tile, so it can be
+//! updated whenever a tile is placed or taken back. The key of a tile on a position is a random
+//! key of the tile multiplied by `X^x * Y^y` modulo a prime. Shifting a board multiplies the sum of
+//! its keys by `X^dx * Y^dy`, so dividing the sum by the powers of the lowest corner of the board
+//! gives the same hash for all shifted copies. The keys are derived from fixed constants instead
+//! of a hasher of the standard library, so the hashes are the same in every build.
+
+use crate::board::MAX_SIZE;
+use crate::nova_luna::Tile;
+
+/// Mersenne prime `2^61 - 1`, the modulus of all keys.
+const MODULUS: u64 = (1 << 61) - 1;
+const X: u64 = 0x0b5a_d4ec_eda1_ce2a;
+const Y: u64 = 0x1f83_d9ab_fb41_bd6b;
+const SEED: u64 = 0x243f_6a88_85a3_08d3;
+
+const X_POWERS: [u64; MAX_SIZE] = powers(X);
+const Y_POWERS: [u64; MAX_SIZE] = powers(Y);
+const X_INVERSE_POWERS: [u64; MAX_SIZE] = powers(pow(X, MODULUS - 2));
+const Y_INVERSE_POWERS: [u64; MAX_SIZE] = powers(pow(Y, MODULUS - 2));
+
+const fn mul(a: u64, b: u64) -> u64 {
+    ((a as u128 * b as u128) % MODULUS as u128) as u64
+}
+
+pub(crate) const fn add(a: u64, b: u64) -> u64 {
+    (a + b) % MODULUS
+}
+
+pub(crate) const fn sub(a: u64, b: u64) -> u64 {
+    (a + MODULUS - b) % MODULUS
+}
+
+const fn pow(base: u64, mut exp: u64) -> u64 {
+    let mut result = 1;
+    let mut base = base % MODULUS;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul(result, base);
+        }
+        base = mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+const fn powers(base: u64) -> [u64; MAX_SIZE] {
+    let mut powers = [1; MAX_SIZE];
+    let mut i = 1;
+    while i < MAX_SIZE {
+        powers[i] = mul(powers[i - 1], base);
+        i += 1;
+    }
+    powers
+}
+
+/// Step of the SplitMix64 generator.
+const fn split_mix(z: u64) -> u64 {
+    let mut z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Random key of the tile, drawn with SplitMix64 from its color, cost and tasks, so a tile has
+/// the same key in every game, whatever its position in the input.
+pub(crate) fn tile_key(tile: &Tile) -> u64 {
+    let mut z = split_mix(SEED ^ tile.color() as u64);
+    z = split_mix(z ^ tile.cost() as u64);
+    z = split_mix(z ^ tile.tasks().len() as u64);
+    for task in tile.tasks() {
+        z = split_mix(z ^ task.colors().len() as u64);
+        for color in task.colors() {
+            z = split_mix(z ^ *color as u64);
+        }
+    }
+    // Zero would not change the hash of a board.
+    z % (MODULUS - 1) + 1
+}
+
+/// Returns the key of a tile with the [tile key](tile_key) placed on the column and row of the
+/// grid.
+pub(crate) fn key(tile_key: u64, (x, y): (usize, usize)) -> u64 {
+    mul(tile_key, mul(X_POWERS[x], Y_POWERS[y]))
+}
+
+/// Returns the hash of a board from the sum of the keys of its tiles and the column and row of its
+/// lowest corner.
+pub(crate) fn normalize(sum: u64, (x, y): (usize, usize)) -> u64 {
+    mul(sum, mul(X_INVERSE_POWERS[x], Y_INVERSE_POWERS[y]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn inverse_powers() {
+        for i in 0..MAX_SIZE {
+            assert_eq!(1, mul(X_POWERS[i], X_INVERSE_POWERS[i]));
+            assert_eq!(1, mul(Y_POWERS[i], Y_INVERSE_POWERS[i]));
+        }
+    }
+}